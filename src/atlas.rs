@@ -0,0 +1,760 @@
+use crate::geometry::PixelRect;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Spine 图集中的一个区域
+///
+/// width/height 为区域未旋转时的尺寸，degrees 不为 0 时在纸张上实际占用的尺寸见 `packed_bounds`。
+#[derive(Debug, Clone)]
+pub struct AtlasRegion {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub degrees: u32,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub original_width: u32,
+    pub original_height: u32,
+    pub index: i32,
+    last_line: usize,
+}
+
+impl AtlasRegion {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            degrees: 0,
+            offset_x: 0,
+            offset_y: 0,
+            original_width: 0,
+            original_height: 0,
+            index: -1,
+            last_line: 0,
+        }
+    }
+
+    /// 区域在纸张上实际占用的像素范围（已考虑旋转）
+    pub fn packed_bounds(&self) -> PixelRect {
+        if self.degrees == 90 || self.degrees == 270 {
+            PixelRect::new(self.x, self.y, self.height, self.width)
+        } else {
+            PixelRect::new(self.x, self.y, self.width, self.height)
+        }
+    }
+
+    /// 去除空白后保留下来的像素在原始（未裁剪）图片中的范围，原始图片坐标以左上角为原点
+    ///
+    /// 图集中的 offset_y 是从原始图片底边算起的，这里换算成从顶边算起。
+    pub fn content_bounds_in_original(&self) -> PixelRect {
+        let top = self.original_height as i32 - self.offset_y - self.height as i32;
+        PixelRect::new(self.offset_x, top, self.width, self.height)
+    }
+
+    /// 把原始图片坐标中的点映射到纸张坐标，落在被裁掉的空白中的点会映射到区域之外
+    pub fn original_point_to_page(&self, x: f32, y: f32) -> (f32, f32) {
+        let content = self.content_bounds_in_original();
+        let local_x = x - content.x as f32;
+        let local_y = y - content.y as f32;
+        let (left, top) = (self.x as f32, self.y as f32);
+        let width = self.width as f32;
+        let height = self.height as f32;
+
+        match self.degrees {
+            90 => (left + local_y, top + width - local_x),
+            180 => (left + width - local_x, top + height - local_y),
+            270 => (left + height - local_y, top + local_x),
+            _ => (left + local_x, top + local_y),
+        }
+    }
+
+    /// 把原始图片坐标中的矩形映射到纸张像素坐标
+    ///
+    /// 落在被裁掉的空白中的部分会被丢弃，完全落在空白中时返回 None。
+    /// 旋转的区域在纸张上是逆时针旋转 degrees 度存放的。
+    pub fn original_rect_to_page(&self, rect: &PixelRect) -> Option<PixelRect> {
+        let content = self.content_bounds_in_original();
+        let visible = rect.intersect(&content)?;
+
+        // 相对于区域内容左上角的坐标
+        let local_x = visible.x - content.x;
+        let local_y = visible.y - content.y;
+        let local_width = visible.width as i32;
+        let local_height = visible.height as i32;
+        let width = self.width as i32;
+        let height = self.height as i32;
+
+        let page_rect = match self.degrees {
+            90 => PixelRect::new(
+                self.x + local_y,
+                self.y + width - local_x - local_width,
+                visible.height,
+                visible.width,
+            ),
+            180 => PixelRect::new(
+                self.x + width - local_x - local_width,
+                self.y + height - local_y - local_height,
+                visible.width,
+                visible.height,
+            ),
+            270 => PixelRect::new(
+                self.x + height - local_y - local_height,
+                self.y + local_x,
+                visible.height,
+                visible.width,
+            ),
+            _ => PixelRect::new(
+                self.x + local_x,
+                self.y + local_y,
+                visible.width,
+                visible.height,
+            ),
+        };
+
+        Some(page_rect)
+    }
+}
+
+/// 图集中的一张纸（对应一张 PNG）
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pma: bool,
+    pub regions: Vec<AtlasRegion>,
+    first_line: usize,
+    last_line: usize,
+}
+
+impl AtlasPage {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            width: 0,
+            height: 0,
+            pma: false,
+            regions: Vec::new(),
+            first_line: 0,
+            last_line: 0,
+        }
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.iter().find(|region| region.name == name)
+    }
+
+    /// 找出与 rect 重叠面积最大的区域
+    pub fn region_containing(&self, rect: &PixelRect) -> Option<&AtlasRegion> {
+        self.regions
+            .iter()
+            .filter_map(|region| {
+                region
+                    .packed_bounds()
+                    .intersect(rect)
+                    .map(|overlap| (overlap.area(), region))
+            })
+            .max_by_key(|(area, _)| *area)
+            .map(|(_, region)| region)
+    }
+}
+
+/// 图集文本格式的版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasFormat {
+    /// Spine 3.x：xy / size / orig / offset，rotate 只有 true / false
+    Spine3,
+    /// Spine 4.x：bounds / offsets，rotate 可以是角度，纸张可以有 pma
+    Spine4,
+}
+
+impl AtlasFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AtlasFormat::Spine3 => "Spine 3.x",
+            AtlasFormat::Spine4 => "Spine 4.x",
+        }
+    }
+
+    /// 对应的 Spine 主版本号
+    pub fn major_version(&self) -> u32 {
+        match self {
+            AtlasFormat::Spine3 => 3,
+            AtlasFormat::Spine4 => 4,
+        }
+    }
+}
+
+/// 图集文件
+///
+/// 原始文本按行保存，重新写出时未修改的行（包括未知字段、顺序、空白和换行符）保持原样，
+/// 新增的内容使用从文件中检测到的版本、缩进和分隔符书写。
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub pages: Vec<AtlasPage>,
+    pub format: AtlasFormat,
+    lines: Vec<String>,
+    style: AtlasStyle,
+}
+
+/// 从文件中检测到的书写风格
+#[derive(Debug, Clone)]
+struct AtlasStyle {
+    page_indent: String,
+    region_indent: String,
+    /// 纸张字段（size）多个值之间的分隔符
+    page_separator: String,
+    /// 区域字段（xy、bounds 等）多个值之间的分隔符，3.x 导出的文件与纸张字段不同
+    region_separator: String,
+    newline: String,
+}
+
+impl Atlas {
+    pub fn load(atlas_path: &str) -> Result<Self> {
+        let text = fs::read_to_string(atlas_path)
+            .with_context(|| format!("Failed to read atlas file: {}", atlas_path))?;
+        Self::parse(&text).with_context(|| format!("Failed to parse atlas file: {}", atlas_path))
+    }
+
+    pub fn save(&self, atlas_path: &str) -> Result<()> {
+        fs::write(atlas_path, self.to_text())
+            .with_context(|| format!("Failed to write atlas file: {}", atlas_path))
+    }
+
+    /// 重新生成图集文本，没有修改过时与读入的文本完全一致
+    pub fn to_text(&self) -> String {
+        self.lines.concat()
+    }
+
+    /// 解析图集文本，同时兼容 Spine 3.x（xy/size/orig/offset）和 4.x（bounds/offsets）的写法
+    pub fn parse(text: &str) -> Result<Self> {
+        let lines: Vec<String> = text.split_inclusive('\n').map(String::from).collect();
+        let mut pages: Vec<AtlasPage> = Vec::new();
+        let mut current_page: Option<AtlasPage> = None;
+        let mut current_region: Option<AtlasRegion> = None;
+        // 空行之后的第一个名称行是纸张名，其余名称行都是区域名
+        let mut expect_page = true;
+        let mut format_hint: Option<AtlasFormat> = None;
+        let mut style = AtlasStyle {
+            page_indent: String::new(),
+            region_indent: String::new(),
+            page_separator: String::new(),
+            region_separator: String::new(),
+            newline: if text.contains("\r\n") { "\r\n" } else { "\n" }.to_string(),
+        };
+        let mut page_indent_found = false;
+        let mut region_indent_found = false;
+        let mut page_separator: Option<String> = None;
+        let mut region_separator: Option<String> = None;
+
+        for (line_number, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                finish_region(&mut current_page, &mut current_region);
+                if let Some(page) = current_page.take() {
+                    pages.push(page);
+                }
+                expect_page = true;
+                continue;
+            }
+
+            match trimmed.split_once(':') {
+                Some((key, value)) => {
+                    let key = key.trim();
+                    let values: Vec<&str> = value.split(',').map(|v| v.trim()).collect();
+                    let indent = &line[..line.len() - line.trim_start().len()];
+
+                    let separator = value.split_once(',').map(|(_, rest)| {
+                        let spaces = rest.len() - rest.trim_start().len();
+                        format!(",{}", &rest[..spaces])
+                    });
+
+                    let result = if let Some(region) = current_region.as_mut() {
+                        if !region_indent_found {
+                            style.region_indent = indent.to_string();
+                            region_indent_found = true;
+                        }
+                        if region_separator.is_none() {
+                            region_separator = separator;
+                        }
+                        if format_hint.is_none() {
+                            format_hint = format_of_region_field(key, &values);
+                        }
+                        region.last_line = line_number;
+                        apply_region_field(region, key, &values)
+                    } else if let Some(page) = current_page.as_mut() {
+                        if !page_indent_found {
+                            style.page_indent = indent.to_string();
+                            page_indent_found = true;
+                        }
+                        if page_separator.is_none() {
+                            page_separator = separator;
+                        }
+                        if key == "pma" && format_hint.is_none() {
+                            format_hint = Some(AtlasFormat::Spine4);
+                        }
+                        page.last_line = line_number;
+                        apply_page_field(page, key, &values)
+                    } else {
+                        Err(anyhow::anyhow!("field appears before any page name"))
+                    };
+                    result.with_context(|| format!("line {}: {}", line_number + 1, trimmed))?;
+                }
+                None => {
+                    if expect_page {
+                        finish_region(&mut current_page, &mut current_region);
+                        if let Some(page) = current_page.take() {
+                            pages.push(page);
+                        }
+                        let mut page = AtlasPage::new(trimmed);
+                        page.first_line = line_number;
+                        page.last_line = line_number;
+                        current_page = Some(page);
+                        expect_page = false;
+                    } else {
+                        finish_region(&mut current_page, &mut current_region);
+                        let mut region = AtlasRegion::new(trimmed);
+                        region.last_line = line_number;
+                        current_region = Some(region);
+                    }
+                }
+            }
+        }
+
+        finish_region(&mut current_page, &mut current_region);
+        if let Some(page) = current_page.take() {
+            pages.push(page);
+        }
+
+        // 只出现过一种多值字段时，另一种沿用它的写法
+        let default_separator = ", ".to_string();
+        style.page_separator = page_separator
+            .clone()
+            .or(region_separator.clone())
+            .unwrap_or(default_separator.clone());
+        style.region_separator = region_separator
+            .or(page_separator)
+            .unwrap_or(default_separator);
+
+        // 区域字段无法判断时，3.x 导出的文件以空行开头
+        let format = format_hint.unwrap_or_else(|| {
+            if text.starts_with('\n') || text.starts_with("\r\n") {
+                AtlasFormat::Spine3
+            } else {
+                AtlasFormat::Spine4
+            }
+        });
+
+        Ok(Self {
+            pages,
+            format,
+            lines,
+            style,
+        })
+    }
+
+    /// 根据 PNG 文件名查找对应的纸张；图集只有一张纸时直接使用它
+    pub fn page_for_image(&self, image_path: &str) -> Option<&AtlasPage> {
+        let file_name = Path::new(image_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())?;
+
+        self.pages
+            .iter()
+            .find(|page| page.name == file_name)
+            .or(if self.pages.len() == 1 {
+                self.pages.first()
+            } else {
+                None
+            })
+    }
+
+    /// 在指定纸张的末尾追加一个区域，使用文件原有的版本格式书写
+    pub fn add_region(&mut self, page_name: &str, region: &AtlasRegion) -> Result<()> {
+        let page = self
+            .pages
+            .iter()
+            .find(|page| page.name == page_name)
+            .ok_or_else(|| anyhow::anyhow!("Atlas page not found: {}", page_name))?;
+
+        let insert_at = page.last_line + 1;
+        self.terminate_line(page.last_line);
+        let new_lines = self.region_lines(region);
+        self.lines.splice(insert_at..insert_at, new_lines);
+        self.reparse()
+    }
+
+    /// 在图集末尾追加一张空白纸张，除 size 以外的纸张字段沿用第一张纸的写法
+    pub fn add_page(&mut self, name: &str, width: u32, height: u32) -> Result<()> {
+        let newline = self.style.newline.clone();
+        let indent = self.style.page_indent.clone();
+        let mut new_lines = vec![format!("{}{}", name, newline)];
+        new_lines.push(format!(
+            "{}size: {}{}{}{}",
+            indent, width, self.style.page_separator, height, newline
+        ));
+
+        if let Some(first_page) = self.pages.first() {
+            // 纸张字段到第一个区域名为止
+            let page_fields = self.lines[first_page.first_line + 1..]
+                .iter()
+                .take_while(|line| line.contains(':'));
+            for line in page_fields {
+                let key = line.trim().split_once(':').map(|(key, _)| key.trim());
+                if key != Some("size") {
+                    new_lines.push(format!("{}{}", line.trim_end(), newline));
+                }
+            }
+        }
+
+        // 纸张之间用空行分隔
+        if let Some(last) = self.lines.len().checked_sub(1) {
+            self.terminate_line(last);
+            if !self.lines[last].trim().is_empty() {
+                self.lines.push(newline);
+            }
+        }
+        self.lines.extend(new_lines);
+        self.reparse()
+    }
+
+    /// 修改纸张引用的图片文件名，只改动纸张名所在的一行
+    pub fn rename_page(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let page = self
+            .pages
+            .iter()
+            .find(|page| page.name == old_name)
+            .ok_or_else(|| anyhow::anyhow!("Atlas page not found: {}", old_name))?;
+
+        let line = &self.lines[page.first_line];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let ending = line_ending(line);
+        self.lines[page.first_line] = format!("{}{}{}", indent, new_name, ending);
+        self.reparse()
+    }
+
+    fn region_lines(&self, region: &AtlasRegion) -> Vec<String> {
+        let indent = &self.style.region_indent;
+        let separator = &self.style.region_separator;
+        let newline = &self.style.newline;
+        let join = |values: &[String]| values.join(separator);
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        match self.format {
+            AtlasFormat::Spine3 => {
+                let rotate = match region.degrees {
+                    0 => "false".to_string(),
+                    90 => "true".to_string(),
+                    degrees => degrees.to_string(),
+                };
+                fields.push(("rotate".to_string(), rotate));
+                fields.push((
+                    "xy".to_string(),
+                    join(&[region.x.to_string(), region.y.to_string()]),
+                ));
+                fields.push((
+                    "size".to_string(),
+                    join(&[region.width.to_string(), region.height.to_string()]),
+                ));
+                fields.push((
+                    "orig".to_string(),
+                    join(&[
+                        region.original_width.to_string(),
+                        region.original_height.to_string(),
+                    ]),
+                ));
+                fields.push((
+                    "offset".to_string(),
+                    join(&[region.offset_x.to_string(), region.offset_y.to_string()]),
+                ));
+                fields.push(("index".to_string(), region.index.to_string()));
+            }
+            AtlasFormat::Spine4 => {
+                fields.push((
+                    "bounds".to_string(),
+                    join(&[
+                        region.x.to_string(),
+                        region.y.to_string(),
+                        region.width.to_string(),
+                        region.height.to_string(),
+                    ]),
+                ));
+                let stripped = region.offset_x != 0
+                    || region.offset_y != 0
+                    || region.original_width != region.width
+                    || region.original_height != region.height;
+                if stripped {
+                    fields.push((
+                        "offsets".to_string(),
+                        join(&[
+                            region.offset_x.to_string(),
+                            region.offset_y.to_string(),
+                            region.original_width.to_string(),
+                            region.original_height.to_string(),
+                        ]),
+                    ));
+                }
+                if region.degrees != 0 {
+                    fields.push(("rotate".to_string(), region.degrees.to_string()));
+                }
+                if region.index != -1 {
+                    fields.push(("index".to_string(), region.index.to_string()));
+                }
+            }
+        }
+
+        let mut lines = vec![format!("{}{}", region.name, newline)];
+        for (key, value) in fields {
+            lines.push(format!("{}{}: {}{}", indent, key, value, newline));
+        }
+        lines
+    }
+
+    /// 文件最后一行可能没有换行符，在它后面插入内容前先补上
+    fn terminate_line(&mut self, index: usize) {
+        if !self.lines[index].ends_with('\n') {
+            let newline = self.style.newline.clone();
+            self.lines[index].push_str(&newline);
+        }
+    }
+
+    fn reparse(&mut self) -> Result<()> {
+        let format = self.format;
+        *self = Self::parse(&self.to_text())?;
+        // 版本以最初读入的文件为准，避免新增内容改变判断结果
+        self.format = format;
+        Ok(())
+    }
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+fn format_of_region_field(key: &str, values: &[&str]) -> Option<AtlasFormat> {
+    match key {
+        "bounds" | "offsets" => Some(AtlasFormat::Spine4),
+        "xy" | "orig" | "offset" => Some(AtlasFormat::Spine3),
+        "rotate" => match values.first().copied() {
+            Some("true") | Some("false") | None => None,
+            Some(_) => Some(AtlasFormat::Spine4),
+        },
+        _ => None,
+    }
+}
+
+fn finish_region(page: &mut Option<AtlasPage>, region: &mut Option<AtlasRegion>) {
+    if let Some(mut region) = region.take() {
+        // 没有 orig/offsets 时，原始尺寸就是区域尺寸
+        if region.original_width == 0 && region.original_height == 0 {
+            region.original_width = region.width;
+            region.original_height = region.height;
+        }
+        if let Some(page) = page.as_mut() {
+            page.last_line = page.last_line.max(region.last_line);
+            page.regions.push(region);
+        }
+    }
+}
+
+fn apply_page_field(page: &mut AtlasPage, key: &str, values: &[&str]) -> Result<()> {
+    match key {
+        "size" => {
+            page.width = parse_number(values, 0)?;
+            page.height = parse_number(values, 1)?;
+        }
+        "pma" => page.pma = values.first() == Some(&"true"),
+        // format / filter / repeat / scale 等字段对绘制没有影响
+        _ => {}
+    }
+    Ok(())
+}
+
+fn apply_region_field(region: &mut AtlasRegion, key: &str, values: &[&str]) -> Result<()> {
+    match key {
+        // 4.x
+        "bounds" => {
+            region.x = parse_number(values, 0)?;
+            region.y = parse_number(values, 1)?;
+            region.width = parse_number(values, 2)?;
+            region.height = parse_number(values, 3)?;
+        }
+        "offsets" => {
+            region.offset_x = parse_number(values, 0)?;
+            region.offset_y = parse_number(values, 1)?;
+            region.original_width = parse_number(values, 2)?;
+            region.original_height = parse_number(values, 3)?;
+        }
+        // 3.x
+        "xy" => {
+            region.x = parse_number(values, 0)?;
+            region.y = parse_number(values, 1)?;
+        }
+        "size" => {
+            region.width = parse_number(values, 0)?;
+            region.height = parse_number(values, 1)?;
+        }
+        "orig" => {
+            region.original_width = parse_number(values, 0)?;
+            region.original_height = parse_number(values, 1)?;
+        }
+        "offset" => {
+            region.offset_x = parse_number(values, 0)?;
+            region.offset_y = parse_number(values, 1)?;
+        }
+        // 两个版本通用
+        "rotate" => {
+            region.degrees = match values.first().copied() {
+                Some("true") => 90,
+                Some("false") | None => 0,
+                Some(value) => value
+                    .parse()
+                    .with_context(|| format!("invalid rotate value: {}", value))?,
+            };
+        }
+        "index" => region.index = parse_number(values, 0)?,
+        // split / pad 等九宫格字段对绘制没有影响
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(values: &[&str], position: usize) -> Result<T> {
+    let value = values
+        .get(position)
+        .ok_or_else(|| anyhow::anyhow!("missing value #{}", position + 1))?;
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid number: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPINE3_ATLAS: &str = "\r\nhero.png\r\nsize: 1024,256\r\nformat: RGBA8888\r\nfilter: Linear,Linear\r\nrepeat: none\r\nbody\r\n  rotate: true\r\n  xy: 2, 2\r\n  size: 100, 60\r\n  orig: 120, 80\r\n  offset: 10, 4\r\n  index: -1\r\nhead\r\n  rotate: false\r\n  xy: 64, 2\r\n  size: 40, 40\r\n  orig: 40, 40\r\n  offset: 0, 0\r\n  index: -1\r\n";
+
+    const SPINE4_ATLAS: &str = "hero.png\nsize:512,256\nfilter:Linear,Linear\npma:true\nbody\nbounds:2,2,100,60\noffsets:10,4,120,80\nrotate:90\nhead\nbounds:64,2,40,40\nrotate:270\nfoot\nbounds:110,2,20,10\n";
+
+    #[test]
+    fn spine3_atlas_round_trips_unchanged() {
+        let atlas = Atlas::parse(SPINE3_ATLAS).unwrap();
+        assert_eq!(atlas.format, AtlasFormat::Spine3);
+        assert_eq!(atlas.to_text(), SPINE3_ATLAS);
+
+        let page = &atlas.pages[0];
+        assert_eq!((page.width, page.height, page.pma), (1024, 256, false));
+        let body = page.region("body").unwrap();
+        assert_eq!((body.x, body.y, body.width, body.height), (2, 2, 100, 60));
+        assert_eq!(body.degrees, 90);
+        assert_eq!((body.offset_x, body.offset_y), (10, 4));
+        assert_eq!((body.original_width, body.original_height), (120, 80));
+        assert_eq!(page.region("head").unwrap().degrees, 0);
+    }
+
+    #[test]
+    fn spine3_added_region_uses_region_style() {
+        let mut atlas = Atlas::parse(SPINE3_ATLAS).unwrap();
+        let mut region = AtlasRegion::new("label");
+        region.x = 110;
+        region.y = 2;
+        region.width = 30;
+        region.height = 20;
+        region.original_width = 30;
+        region.original_height = 20;
+        atlas.add_region("hero.png", &region).unwrap();
+
+        let text = atlas.to_text();
+        assert!(text.starts_with(SPINE3_ATLAS));
+        assert_eq!(
+            &text[SPINE3_ATLAS.len()..],
+            "label\r\n  rotate: false\r\n  xy: 110, 2\r\n  size: 30, 20\r\n  orig: 30, 20\r\n  offset: 0, 0\r\n  index: -1\r\n"
+        );
+        assert_eq!(atlas.pages[0].regions.len(), 3);
+        assert_eq!(atlas.format, AtlasFormat::Spine3);
+    }
+
+    #[test]
+    fn spine4_atlas_round_trips_unchanged() {
+        let atlas = Atlas::parse(SPINE4_ATLAS).unwrap();
+        assert_eq!(atlas.format, AtlasFormat::Spine4);
+        assert_eq!(atlas.to_text(), SPINE4_ATLAS);
+
+        let page = &atlas.pages[0];
+        assert_eq!((page.width, page.height, page.pma), (512, 256, true));
+        let body = page.region("body").unwrap();
+        assert_eq!(body.degrees, 90);
+        assert_eq!((body.offset_x, body.offset_y), (10, 4));
+        assert_eq!((body.original_width, body.original_height), (120, 80));
+        assert_eq!(page.region("head").unwrap().degrees, 270);
+        let foot = page.region("foot").unwrap();
+        assert_eq!((foot.original_width, foot.original_height), (20, 10));
+    }
+
+    #[test]
+    fn spine4_added_region_and_page_keep_format() {
+        let mut atlas = Atlas::parse(SPINE4_ATLAS).unwrap();
+        let mut region = AtlasRegion::new("label");
+        region.x = 140;
+        region.y = 2;
+        region.width = 30;
+        region.height = 20;
+        region.original_width = 30;
+        region.original_height = 20;
+        region.degrees = 90;
+        atlas.add_region("hero.png", &region).unwrap();
+        atlas.add_page("hero_labels.png", 64, 32).unwrap();
+
+        assert_eq!(
+            atlas.to_text(),
+            format!(
+                "{}label\nbounds: 140,2,30,20\nrotate: 90\n\nhero_labels.png\nsize: 64,32\nfilter:Linear,Linear\npma:true\n",
+                SPINE4_ATLAS
+            )
+        );
+        assert_eq!(atlas.pages.len(), 2);
+        assert!(atlas.pages[1].pma);
+        assert_eq!(atlas.pages[0].region("label").unwrap().degrees, 90);
+    }
+
+    #[test]
+    fn original_rect_maps_onto_rotated_region() {
+        let atlas = Atlas::parse(SPINE4_ATLAS).unwrap();
+        // 纸张上占 60x100，原始图片 120x80，去掉左侧 10 像素和底部 4 像素的空白
+        let body = atlas.pages[0].region("body").unwrap();
+        assert_eq!(body.packed_bounds(), PixelRect::new(2, 2, 60, 100));
+        assert_eq!(
+            body.content_bounds_in_original(),
+            PixelRect::new(10, 16, 100, 60)
+        );
+
+        // 原始图片左上角的内容在纸张上位于区域的左下角
+        assert_eq!(
+            body.original_rect_to_page(&PixelRect::new(10, 16, 20, 10)),
+            Some(PixelRect::new(2, 82, 10, 20))
+        );
+        assert_eq!(body.original_point_to_page(10.0, 16.0), (2.0, 102.0));
+
+        // 落在空白中的部分被丢弃
+        assert_eq!(
+            body.original_rect_to_page(&PixelRect::new(0, 0, 30, 26)),
+            Some(PixelRect::new(2, 82, 10, 20))
+        );
+        assert_eq!(
+            body.original_rect_to_page(&PixelRect::new(0, 0, 10, 80)),
+            None
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::atlas::Atlas;
+use crate::skeleton::{self, SkeletonFile, SkeletonFormat};
 use anyhow::Result;
 use std::fs;
 use std::path::Path;
@@ -5,7 +7,8 @@ use std::path::Path;
 pub struct FileManager {
     output_dir: String,
     spine_name: String,
-    has_additional_files: bool, // 是否有其他同名文件（如.atlas, .skel）
+    has_additional_files: bool, // 是否有其他同名文件（如.atlas, .skel, .json）
+    rename_variants: bool,      // 子目录中的文件是否按变体命名（如 hero_07.atlas）
 }
 
 impl FileManager {
@@ -14,6 +17,21 @@ impl FileManager {
             output_dir: output_dir.to_string(),
             spine_name: spine_name.to_string(),
             has_additional_files,
+            rename_variants: false,
+        }
+    }
+
+    /// 子目录中的图集、图片和骨骼文件改用子目录名（如 `hero_07.*`）命名
+    pub fn set_rename_variants(&mut self, rename_variants: bool) {
+        self.rename_variants = rename_variants;
+    }
+
+    /// 子目录中文件的主名
+    fn file_stem<'a>(&'a self, dir_name: &'a str) -> &'a str {
+        if self.rename_variants {
+            dir_name
+        } else {
+            &self.spine_name
         }
     }
 
@@ -50,28 +68,51 @@ impl FileManager {
     pub fn copy_files(
         &self,
         dir_name: &str,
-        atlas_path: &str,
-        skel_path: &str,
-        has_atlas: bool,
-        has_skel: bool,
+        atlas: Option<&Atlas>,
+        skeletons: &[SkeletonFile],
     ) -> Result<()> {
         // 如果没有其他文件，不需要复制任何文件
         if !self.has_additional_files {
             return Ok(());
         }
 
-        let target_dir = format!("{}/{}", self.output_dir, dir_name);
-
-        // 如果atlas文件存在，则复制
-        if has_atlas {
-            let atlas_target = format!("{}/{}.atlas", target_dir, self.spine_name);
-            fs::copy(atlas_path, &atlas_target)?;
+        // 如果atlas文件存在，则通过图集写出（未修改的内容与原文件逐字节一致）
+        if let Some(atlas) = atlas {
+            let file_stem = self.file_stem(dir_name);
+            let atlas_target = self.atlas_target(dir_name);
+            let png_name = format!("{}.png", self.spine_name);
+            match atlas.page_for_image(&png_name) {
+                // 图片改名后，图集中引用它的纸张名随之修改
+                Some(page) if self.rename_variants => {
+                    let mut atlas = atlas.clone();
+                    let old_name = page.name.clone();
+                    let new_name = match old_name.rsplit_once('/') {
+                        Some((page_dir, _)) => format!("{}/{}.png", page_dir, file_stem),
+                        None => format!("{}.png", file_stem),
+                    };
+                    atlas.rename_page(&old_name, &new_name)?;
+                    atlas.save(&atlas_target)?;
+                }
+                _ => atlas.save(&atlas_target)?,
+            }
         }
 
-        // 如果skel文件存在，则复制
-        if has_skel {
-            let skel_target = format!("{}/{}.skel", target_dir, self.spine_name);
-            fs::copy(skel_path, &skel_target)?;
+        // 复制骨骼文件（.skel 和 .json 都会复制）
+        for skeleton in skeletons {
+            let target = self.skeleton_target(dir_name, skeleton.format);
+            fs::copy(&skeleton.path, &target)?;
+
+            // 图片目录以原名命名时（如 ./hero/），同样改为变体名
+            if self.rename_variants {
+                if let Some(images_path) = skeleton::rename_images_path(
+                    &target,
+                    skeleton.format,
+                    &self.spine_name,
+                    dir_name,
+                )? {
+                    println!("Skeleton images path of {}: {}", target, images_path);
+                }
+            }
         }
 
         // PNG文件会在图片处理模块中处理，这里不需要复制
@@ -79,6 +120,37 @@ impl FileManager {
         Ok(())
     }
 
+    /// 子目录中图集文件的路径
+    pub fn atlas_target(&self, dir_name: &str) -> String {
+        format!(
+            "{}/{}/{}.atlas",
+            self.output_dir,
+            dir_name,
+            self.file_stem(dir_name)
+        )
+    }
+
+    /// 子目录中骨骼文件的路径
+    pub fn skeleton_target(&self, dir_name: &str, format: SkeletonFormat) -> String {
+        format!(
+            "{}/{}/{}.{}",
+            self.output_dir,
+            dir_name,
+            self.file_stem(dir_name),
+            format.extension()
+        )
+    }
+
+    /// 子目录中图片的路径
+    pub fn png_target(&self, dir_name: &str) -> String {
+        format!(
+            "{}/{}/{}.png",
+            self.output_dir,
+            dir_name,
+            self.file_stem(dir_name)
+        )
+    }
+
     // 保留旧方法以保持向后兼容
     pub fn copy_spine_files(
         &self,
@@ -87,6 +159,11 @@ impl FileManager {
         _png_path: &str,
         skel_path: &str,
     ) -> Result<()> {
-        self.copy_files(dir_name, atlas_path, skel_path, true, true)
+        let atlas = Atlas::load(atlas_path)?;
+        let skeleton = SkeletonFile {
+            path: skel_path.to_string(),
+            format: SkeletonFormat::Binary,
+        };
+        self.copy_files(dir_name, Some(&atlas), &[skeleton])
     }
 }
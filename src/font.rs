@@ -0,0 +1,390 @@
+use anyhow::{Context, Result};
+use rusttype::{Font, GlyphId, PositionedGlyph, Scale};
+use std::borrow::Cow;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use unicode_bidi::BidiInfo;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+/// 主字体缺字时依次尝试的系统字体，与 GUI 界面使用的中文字体相同，另外加上 Linux 常见的 CJK 字体
+const SYSTEM_FALLBACK_FONTS: [&str; 7] = [
+    "/System/Library/Fonts/PingFang.ttc",         // macOS 苹方字体
+    "/System/Library/Fonts/STHeiti Light.ttc",    // macOS 黑体
+    "/System/Library/Fonts/Hiragino Sans GB.ttc", // macOS 冬青黑体
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc", // Linux 思源黑体
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc", // Linux 文泉驿微米黑
+    "C:\\Windows\\Fonts\\msyh.ttc",               // Windows 微软雅黑
+    "C:\\Windows\\Fonts\\simhei.ttf",             // Windows 黑体
+];
+
+/// 内置字体：DejaVu Sans 的拉丁字母子集（Basic Latin 与 Latin-1），没有任何系统字体时使用
+const BUNDLED_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Latin.ttf");
+
+/// 以字体路径指定内置字体时使用的名称，如 `--font "<bundled>"`
+pub const BUNDLED_FONT_PATH: &str = "<bundled>";
+
+/// 未指定字体时依次尝试的系统字体
+const SYSTEM_FONTS: [&str; 4] = [
+    "/System/Library/Fonts/Arial.ttf",                 // macOS
+    "/System/Library/Fonts/Helvetica.ttc",             // macOS
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf", // Linux
+    "C:\\Windows\\Fonts\\arial.ttf",                   // Windows
+];
+
+/// 绘制文字所用的字体文件；.ttc/.otc 字体集合用 index 指定其中的一个字体
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontChoice {
+    pub path: String,
+    pub index: u32,
+}
+
+impl FontChoice {
+    /// 解析 `path` 或 `path:index`；冒号后不是数字时整体视为路径（如 Windows 的 `C:\...`）
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.is_empty() {
+            return Err("expected a font path".to_string());
+        }
+        if let Some((path, index)) = value.rsplit_once(':') {
+            if let Ok(index) = index.parse::<u32>() {
+                return Ok(Self {
+                    path: path.to_string(),
+                    index,
+                });
+            }
+        }
+        Ok(Self {
+            path: value.to_string(),
+            index: 0,
+        })
+    }
+}
+
+/// 已加载的字体及其来源
+#[derive(Clone)]
+pub struct LoadedFont {
+    pub choice: FontChoice,
+    /// 字体文件中的字体数量，普通字体文件为 1
+    pub face_count: u32,
+    pub font: Font<'static>,
+    /// 字体文件内容，整形时交给 rustybuzz 解析
+    data: Arc<[u8]>,
+}
+
+impl LoadedFont {
+    pub fn load(choice: &FontChoice) -> Result<Self> {
+        let data = if choice.path == BUNDLED_FONT_PATH {
+            Cow::Borrowed(BUNDLED_FONT)
+        } else {
+            Cow::Owned(
+                fs::read(&choice.path)
+                    .with_context(|| format!("Failed to read font file: {}", choice.path))?,
+            )
+        };
+        let face_count = face_count(&data);
+        let shaping_data = Arc::from(&*data);
+        if choice.index >= face_count {
+            anyhow::bail!(
+                "Font file {} contains {} face(s), face index {} is out of range",
+                choice.path,
+                face_count,
+                choice.index
+            );
+        }
+
+        let font = match data {
+            Cow::Borrowed(data) => Font::try_from_bytes_and_index(data, choice.index),
+            Cow::Owned(data) => Font::try_from_vec_and_index(data, choice.index),
+        };
+        let font = font.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Failed to parse font: {} (face {})",
+                choice.path,
+                choice.index
+            )
+        })?;
+
+        Ok(Self {
+            choice: choice.clone(),
+            face_count,
+            font,
+            data: shaping_data,
+        })
+    }
+
+    /// 指定了字体时加载该字体，否则使用第一个能加载的系统字体，都没有时使用内置字体
+    pub fn load_or_system(choice: Option<&FontChoice>) -> Result<Self> {
+        match choice {
+            Some(choice) => Self::load(choice),
+            None => Self::load_system(),
+        }
+    }
+
+    fn load_system() -> Result<Self> {
+        for font_path in SYSTEM_FONTS {
+            if !Path::new(font_path).exists() {
+                continue;
+            }
+            let choice = FontChoice {
+                path: font_path.to_string(),
+                index: 0,
+            };
+            if let Ok(font) = Self::load(&choice) {
+                return Ok(font);
+            }
+        }
+
+        println!(
+            "Warning: no system font found, using the bundled DejaVu Sans subset (Latin characters only)"
+        );
+        Self::load(&FontChoice {
+            path: BUNDLED_FONT_PATH.to_string(),
+            index: 0,
+        })
+    }
+
+    /// 例如 `/fonts/brand.ttc (face 2 of 4)`
+    pub fn describe(&self) -> String {
+        if self.face_count > 1 {
+            format!(
+                "{} (face {} of {})",
+                self.choice.path, self.choice.index, self.face_count
+            )
+        } else {
+            self.choice.path.clone()
+        }
+    }
+}
+
+/// 按顺序逐字选择字体的字体链：主字体没有的字形依次到后备字体中查找
+#[derive(Clone)]
+pub struct FontStack {
+    /// 第一个是主字体，字号、基线和大写字母高度都以它为准
+    fonts: Vec<LoadedFont>,
+}
+
+impl FontStack {
+    /// 加载主字体和 fallbacks；system_fallbacks 为 true 时再追加存在的系统 CJK 字体
+    pub fn load(
+        primary: Option<&FontChoice>,
+        fallbacks: &[FontChoice],
+        system_fallbacks: bool,
+    ) -> Result<Self> {
+        let mut fonts = vec![LoadedFont::load_or_system(primary)?];
+        for fallback in fallbacks {
+            fonts.push(LoadedFont::load(fallback)?);
+        }
+
+        if system_fallbacks {
+            for font_path in SYSTEM_FALLBACK_FONTS {
+                if !Path::new(font_path).exists()
+                    || fonts.iter().any(|font| font.choice.path == font_path)
+                {
+                    continue;
+                }
+                let choice = FontChoice {
+                    path: font_path.to_string(),
+                    index: 0,
+                };
+                if let Ok(font) = LoadedFont::load(&choice) {
+                    fonts.push(font);
+                }
+            }
+        }
+
+        Ok(Self { fonts })
+    }
+
+    /// 替换主字体，后备字体保持不变
+    pub fn set_primary(&mut self, primary: LoadedFont) {
+        self.fonts[0] = primary;
+    }
+
+    pub fn primary(&self) -> &LoadedFont {
+        &self.fonts[0]
+    }
+
+    pub fn fallbacks(&self) -> &[LoadedFont] {
+        &self.fonts[1..]
+    }
+
+    /// 例如 `/fonts/brand.ttf, fallback: /System/Library/Fonts/PingFang.ttc`
+    pub fn describe(&self) -> String {
+        let mut description = self.primary().describe();
+        if !self.fallbacks().is_empty() {
+            let fallbacks: Vec<String> =
+                self.fallbacks().iter().map(LoadedFont::describe).collect();
+            description.push_str(&format!(", fallback: {}", fallbacks.join(", ")));
+        }
+        description
+    }
+
+    /// 所有字体都没有的字形报错，避免输出中出现空白或方框
+    pub fn check_glyphs(&self, text: &str) -> Result<()> {
+        let missing: Vec<String> = text
+            .chars()
+            .filter(|c| !c.is_control() && !c.is_whitespace())
+            .filter(|&c| self.font_for(c).is_none())
+            .map(|c| format!("'{}' (U+{:04X})", c, c as u32))
+            .collect();
+        if !missing.is_empty() {
+            let fonts: Vec<String> = self.fonts.iter().map(LoadedFont::describe).collect();
+            anyhow::bail!(
+                "No font has a glyph for {} in \"{}\", add one with --fallback-font (fonts tried: {})",
+                missing.join(", "),
+                text,
+                fonts.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// 单行排版：先按双向算法把文字分成从左到右排列的方向段，每段再按字体分段，
+    /// 用 rustybuzz 整形（连写、字形替换、组合符号定位和字距调整），字形从基线起点向右排列
+    pub fn layout(&self, text: &str, scale: Scale) -> Vec<PositionedGlyph<'static>> {
+        let mut glyphs = Vec::new();
+        let mut caret = 0.0;
+        for (range, rtl) in visual_runs(text) {
+            let mut runs = self.font_runs(text, range);
+            // 从右到左的段中，按字体分出的小段也要倒序排列
+            if rtl {
+                runs.reverse();
+            }
+            for (index, range) in runs {
+                self.shape_run(index, &text[range], rtl, scale, &mut caret, &mut glyphs);
+            }
+        }
+        glyphs
+    }
+
+    /// 把 range 内的文字按字体分段（控制字符不绘制）
+    ///
+    /// 每个字符用第一个包含它的字体；空格、标点和组合符号只要前一段的字体有，
+    /// 就并入前一段，以便一起整形。
+    fn font_runs(&self, text: &str, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for (offset, c) in text[range.clone()].char_indices() {
+            if c.is_control() {
+                continue;
+            }
+            let start = range.start + offset;
+            let end = start + c.len_utf8();
+            let font_index = self.font_for(c).unwrap_or(0);
+            match runs.last_mut() {
+                Some((index, run))
+                    if run.end == start
+                        && (font_index == *index
+                            || (joins_run(c) && self.has_glyph(*index, c))) =>
+                {
+                    run.end = end;
+                }
+                _ => runs.push((font_index, start..end)),
+            }
+        }
+        runs
+    }
+
+    /// 用第 index 个字体整形一段同方向的文字，把字形追加到 glyphs 并移动 caret
+    fn shape_run(
+        &self,
+        index: usize,
+        text: &str,
+        rtl: bool,
+        scale: Scale,
+        caret: &mut f32,
+        glyphs: &mut Vec<PositionedGlyph<'static>>,
+    ) {
+        let loaded = &self.fonts[index];
+        let Some(face) = rustybuzz::Face::from_slice(&loaded.data, loaded.choice.index) else {
+            // rustybuzz 无法解析的字体退回逐字排列
+            for c in text.chars() {
+                let glyph = loaded.font.glyph(c).scaled(scale);
+                let advance_width = glyph.h_metrics().advance_width;
+                glyphs.push(glyph.positioned(rusttype::point(*caret, 0.0)));
+                *caret += advance_width;
+            }
+            return;
+        };
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(if rtl {
+            rustybuzz::Direction::RightToLeft
+        } else {
+            rustybuzz::Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(&face, &[], buffer);
+
+        // 与 rusttype 相同，字号对应 ascent 到 descent 的高度
+        let v_metrics = loaded.font.v_metrics_unscaled();
+        let units = v_metrics.ascent - v_metrics.descent;
+        let (scale_x, scale_y) = (scale.x / units, scale.y / units);
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            // 整形结果的 y 轴向上，图片的 y 轴向下
+            let glyph = loaded
+                .font
+                .glyph(GlyphId(info.glyph_id as u16))
+                .scaled(scale)
+                .positioned(rusttype::point(
+                    *caret + position.x_offset as f32 * scale_x,
+                    -position.y_offset as f32 * scale_y,
+                ));
+            glyphs.push(glyph);
+            *caret += position.x_advance as f32 * scale_x;
+        }
+    }
+
+    /// 第 index 个字体是否包含该字符（0 号字形是缺字时的占位字形）
+    fn has_glyph(&self, index: usize, c: char) -> bool {
+        self.fonts[index].font.glyph(c).id().0 != 0
+    }
+
+    /// 第一个包含该字符的字体的序号
+    fn font_for(&self, c: char) -> Option<usize> {
+        (0..self.fonts.len()).find(|&index| self.has_glyph(index, c))
+    }
+}
+
+/// 空格、标点和组合符号，跟随前面的文字使用同一个字体
+fn joins_run(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            get_general_category(c),
+            GeneralCategory::ConnectorPunctuation
+                | GeneralCategory::DashPunctuation
+                | GeneralCategory::OpenPunctuation
+                | GeneralCategory::ClosePunctuation
+                | GeneralCategory::InitialPunctuation
+                | GeneralCategory::FinalPunctuation
+                | GeneralCategory::OtherPunctuation
+                | GeneralCategory::NonspacingMark
+                | GeneralCategory::SpacingMark
+                | GeneralCategory::EnclosingMark
+        )
+}
+
+/// 按 Unicode 双向算法得到的方向段（字节范围和是否从右到左），按从左到右的显示顺序排列
+fn visual_runs(text: &str) -> Vec<(Range<usize>, bool)> {
+    let bidi_info = BidiInfo::new(text, None);
+    let mut runs = Vec::new();
+    for paragraph in &bidi_info.paragraphs {
+        let (levels, paragraph_runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+        runs.extend(paragraph_runs.into_iter().map(|run| {
+            let rtl = levels[run.start].is_rtl();
+            (run, rtl)
+        }));
+    }
+    runs
+}
+
+/// 字体文件中的字体数量：字体集合（ttcf）的头部记录了数量，普通字体文件只有一个
+pub fn face_count(data: &[u8]) -> u32 {
+    match data.get(..12) {
+        Some(header) if &header[..4] == b"ttcf" => {
+            u32::from_be_bytes([header[8], header[9], header[10], header[11]])
+        }
+        _ => 1,
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// 图片像素坐标系中的矩形（左上角为原点，y轴向下）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PixelRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// 判断 other 是否完全位于当前矩形内
+    pub fn contains_rect(&self, other: &PixelRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    /// 两个矩形的交集，没有交集时返回 None
+    pub fn intersect(&self, other: &PixelRect) -> Option<PixelRect> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = self.right().min(other.right());
+        let y1 = self.bottom().min(other.bottom());
+
+        if x1 > x0 && y1 > y0 {
+            Some(PixelRect::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32))
+        } else {
+            None
+        }
+    }
+
+    /// 四边各向内收缩 amount 像素
+    pub fn inset(&self, amount: u32) -> PixelRect {
+        let amount = amount as i32;
+        let width = (self.width as i32 - amount * 2).max(0) as u32;
+        let height = (self.height as i32 - amount * 2).max(0) as u32;
+        PixelRect::new(self.x + amount, self.y + amount, width, height)
+    }
+}
+
+/// 矩形范围内逐像素的可见性标记
+#[derive(Debug, Clone)]
+pub struct PixelMask {
+    pub bounds: PixelRect,
+    bits: Vec<bool>,
+}
+
+impl PixelMask {
+    /// 创建一个全部不可见的遮罩
+    pub fn new(bounds: PixelRect) -> Self {
+        Self {
+            bounds,
+            bits: vec![false; bounds.area() as usize],
+        }
+    }
+
+    /// 范围外的像素视为不可见
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        if !self.bounds.contains(x, y) {
+            return false;
+        }
+        let index = (y - self.bounds.y) as usize * self.bounds.width as usize
+            + (x - self.bounds.x) as usize;
+        self.bits[index]
+    }
+
+    /// rect 中可见像素的数量
+    pub fn count_in(&self, rect: &PixelRect) -> u64 {
+        let Some(overlap) = rect.intersect(&self.bounds) else {
+            return 0;
+        };
+        let mut count = 0;
+        for y in overlap.y..overlap.bottom() {
+            for x in overlap.x..overlap.right() {
+                if self.contains(x, y) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 把像素中心落在三角形内（含边上）的像素标记为可见
+    pub fn fill_triangle(&mut self, a: (f32, f32), b: (f32, f32), c: (f32, f32)) {
+        let edge = |p: (f32, f32), q: (f32, f32), x: f32, y: f32| {
+            (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)
+        };
+        let area = edge(a, b, c.0, c.1);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = (a.0.min(b.0).min(c.0).floor() as i32).max(self.bounds.x);
+        let max_x = (a.0.max(b.0).max(c.0).ceil() as i32).min(self.bounds.right());
+        let min_y = (a.1.min(b.1).min(c.1).floor() as i32).max(self.bounds.y);
+        let max_y = (a.1.max(b.1).max(c.1).ceil() as i32).min(self.bounds.bottom());
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                // 三条边的符号都与面积相同时在三角形内，与顶点顺序无关
+                let inside = [edge(a, b, px, py), edge(b, c, px, py), edge(c, a, px, py)]
+                    .iter()
+                    .all(|value| *value * area >= 0.0);
+                if inside {
+                    let index = (y - self.bounds.y) as usize * self.bounds.width as usize
+                        + (x - self.bounds.x) as usize;
+                    self.bits[index] = true;
+                }
+            }
+        }
+    }
+}
+
+/// 解析 x,y,width,height 格式的矩形，用于命令行参数
+impl FromStr for PixelRect {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split(',').map(|part| part.trim()).collect();
+        if parts.len() != 4 {
+            return Err("expected x,y,width,height".to_string());
+        }
+
+        let invalid = |part: &str| format!("invalid number: {}", part);
+        let x = parts[0].parse().map_err(|_| invalid(parts[0]))?;
+        let y = parts[1].parse().map_err(|_| invalid(parts[1]))?;
+        let width = parts[2].parse().map_err(|_| invalid(parts[2]))?;
+        let height = parts[3].parse().map_err(|_| invalid(parts[3]))?;
+        Ok(PixelRect::new(x, y, width, height))
+    }
+}
+
+impl fmt::Display for PixelRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "x={}, y={}, width={}, height={}",
+            self.x, self.y, self.width, self.height
+        )
+    }
+}
@@ -1,11 +1,23 @@
+use crate::font::{FontChoice, LoadedFont};
 use crate::gui::{CoordinateCalculator, ImageDisplay, SelectionHandler, TextDirection};
+use crate::image_processor::OutlineJoin;
 use eframe::egui;
 
-pub struct ControlPanel;
+pub struct ControlPanel {
+    /// 字体输入框的内容，点击 Apply 后才生效
+    font_path_input: String,
+    font_index_input: u32,
+    /// 当前字体的说明，或加载失败的原因
+    font_status: Result<String, String>,
+}
 
 impl ControlPanel {
-    pub fn new() -> Self {
-        Self
+    pub fn new(font: Option<&FontChoice>) -> Self {
+        Self {
+            font_path_input: font.map(|font| font.path.clone()).unwrap_or_default(),
+            font_index_input: font.map(|font| font.index).unwrap_or(0),
+            font_status: describe_font(font),
+        }
     }
 
     pub fn show(
@@ -170,6 +182,55 @@ impl ControlPanel {
 
                 ui.add_space(10.0);
 
+                // 文字角度区域
+                ui.group(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Text Rotation");
+                        ui.add_space(10.0);
+
+                        // 拨盘和输入框显示文字的实际角度（朝向加额外角度），修改后只保存额外角度
+                        let direction_degrees =
+                            selection_handler.get_text_direction().clockwise_degrees() as f32;
+                        let mut angle = normalize_degrees(
+                            direction_degrees + selection_handler.get_text_angle(),
+                        );
+
+                        ui.horizontal(|ui| {
+                            ui.set_min_width(available_width - 20.0); // 设置最小宽度保持一致
+                            let mut changed = angle_dial(ui, &mut angle, 80.0).changed();
+                            ui.vertical(|ui| {
+                                ui.label("Angle:");
+                                changed |= ui
+                                    .add(
+                                        egui::DragValue::new(&mut angle)
+                                            .clamp_range(-180.0..=180.0)
+                                            .speed(1.0)
+                                            .suffix("°"),
+                                    )
+                                    .changed();
+                                if ui.button("Reset").clicked() {
+                                    angle = direction_degrees;
+                                    changed = true;
+                                }
+                            });
+                            if changed {
+                                selection_handler
+                                    .set_text_angle(normalize_degrees(angle - direction_degrees));
+                            }
+                        });
+
+                        ui.label("Drag the dial to rotate, hold Shift to snap to 15°");
+
+                        ui.horizontal(|ui| {
+                            let mut mirror = selection_handler.get_mirror();
+                            ui.checkbox(&mut mirror, "Mirror Text");
+                            selection_handler.set_mirror(mirror);
+                        });
+                    });
+                });
+
+                ui.add_space(10.0);
+
                 // 文字颜色选择区域
                 ui.group(|ui| {
                     ui.vertical(|ui| {
@@ -197,6 +258,137 @@ impl ControlPanel {
 
                 ui.add_space(10.0);
 
+                // 字体区域
+                ui.group(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Font");
+                        ui.add_space(10.0);
+
+                        ui.horizontal(|ui| {
+                            ui.label("File:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.font_path_input)
+                                    .hint_text("system font")
+                                    .desired_width(available_width - 60.0),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Face index:");
+                            ui.add(egui::DragValue::new(&mut self.font_index_input).speed(0.1));
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Apply").clicked() {
+                                let path = self.font_path_input.trim();
+                                let font = (!path.is_empty()).then(|| FontChoice {
+                                    path: path.to_string(),
+                                    index: self.font_index_input,
+                                });
+                                // 只有能加载的字体才交给选择结果和预览
+                                self.font_status = describe_font(font.as_ref());
+                                if self.font_status.is_ok() {
+                                    selection_handler.set_font(font);
+                                }
+                            }
+                            if ui.button("Default").clicked() {
+                                self.font_path_input.clear();
+                                self.font_index_input = 0;
+                                self.font_status = describe_font(None);
+                                selection_handler.set_font(None);
+                            }
+                        });
+
+                        match &self.font_status {
+                            Ok(description) => {
+                                ui.label(format!("Using {}", description));
+                            }
+                            Err(error) => {
+                                ui.colored_label(egui::Color32::RED, error);
+                            }
+                        }
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                // 描边和投影区域
+                ui.group(|ui| {
+                    ui.vertical(|ui| {
+                        ui.heading("Text Effects");
+                        ui.add_space(10.0);
+
+                        let mut enable_outline = selection_handler.get_enable_outline();
+                        ui.checkbox(&mut enable_outline, "Outline");
+                        selection_handler.set_enable_outline(enable_outline);
+
+                        if enable_outline {
+                            let mut outline = selection_handler.get_outline();
+                            ui.horizontal(|ui| {
+                                ui.label("Width:");
+                                ui.add(
+                                    egui::DragValue::new(&mut outline.width)
+                                        .clamp_range(0.0..=20.0)
+                                        .speed(0.1)
+                                        .suffix(" px"),
+                                );
+                                egui::color_picker::color_edit_button_srgba(
+                                    ui,
+                                    &mut outline.color,
+                                    egui::color_picker::Alpha::Opaque,
+                                );
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Join:");
+                                egui::ComboBox::from_id_source("outline_join")
+                                    .selected_text(outline.join.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for join in
+                                            [OutlineJoin::Round, OutlineJoin::Miter, OutlineJoin::Bevel]
+                                        {
+                                            ui.selectable_value(&mut outline.join, join, join.as_str());
+                                        }
+                                    });
+                            });
+                            selection_handler.set_outline(outline);
+                        }
+
+                        ui.add_space(5.0);
+
+                        let mut enable_shadow = selection_handler.get_enable_shadow();
+                        ui.checkbox(&mut enable_shadow, "Drop Shadow");
+                        selection_handler.set_enable_shadow(enable_shadow);
+
+                        if enable_shadow {
+                            let mut shadow = selection_handler.get_shadow();
+                            ui.horizontal(|ui| {
+                                ui.label("Offset:");
+                                ui.add(egui::DragValue::new(&mut shadow.offset_x).speed(0.1).prefix("x "));
+                                ui.add(egui::DragValue::new(&mut shadow.offset_y).speed(0.1).prefix("y "));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Blur:");
+                                ui.add(
+                                    egui::DragValue::new(&mut shadow.blur)
+                                        .clamp_range(0.0..=20.0)
+                                        .speed(0.1)
+                                        .suffix(" px"),
+                                );
+                                egui::color_picker::color_edit_button_srgba(
+                                    ui,
+                                    &mut shadow.color,
+                                    egui::color_picker::Alpha::Opaque,
+                                );
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Opacity:");
+                                ui.add(egui::Slider::new(&mut shadow.opacity, 0.0..=1.0));
+                            });
+                            selection_handler.set_shadow(shadow);
+                        }
+                    });
+                });
+
+                ui.add_space(10.0);
+
                 // 下部：颜色变化区域
                 ui.group(|ui| {
                     ui.vertical(|ui| {
@@ -225,3 +417,71 @@ impl ControlPanel {
             });
     }
 }
+
+/// 加载字体，成功时返回字体说明，失败时返回错误信息
+fn describe_font(font: Option<&FontChoice>) -> Result<String, String> {
+    LoadedFont::load_or_system(font)
+        .map(|loaded| loaded.describe())
+        .map_err(|e| e.to_string())
+}
+
+/// 圆形角度拨盘：指针指向文字的书写方向，0 度指向右侧，顺时针为正
+fn angle_dial(ui: &mut egui::Ui, angle: &mut f32, size: f32) -> egui::Response {
+    let (rect, mut response) =
+        ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+
+    if response.clicked() || response.dragged() {
+        if let Some(pointer_pos) = response.interact_pointer_pos() {
+            let offset = pointer_pos - rect.center();
+            // 点在圆心附近时方向不稳定，忽略
+            if offset.length() > 2.0 {
+                let degrees = offset.y.atan2(offset.x).to_degrees();
+                *angle = if ui.input(|input| input.modifiers.shift) {
+                    normalize_degrees((degrees / 15.0).round() * 15.0)
+                } else {
+                    degrees.round()
+                };
+                response.mark_changed();
+            }
+        }
+    }
+
+    let painter = ui.painter();
+    let center = rect.center();
+    let radius = size / 2.0 - 2.0;
+    let visuals = ui.style().interact(&response);
+    painter.circle(center, radius, visuals.bg_fill, visuals.fg_stroke);
+
+    // 每 45 度一个刻度
+    for step in 0..8 {
+        let tick = (step as f32 * 45.0).to_radians();
+        let direction = egui::vec2(tick.cos(), tick.sin());
+        painter.line_segment(
+            [
+                center + direction * radius * 0.8,
+                center + direction * radius,
+            ],
+            visuals.fg_stroke,
+        );
+    }
+
+    let needle = angle.to_radians();
+    let direction = egui::vec2(needle.cos(), needle.sin());
+    painter.line_segment(
+        [center, center + direction * radius * 0.9],
+        egui::Stroke::new(2.0, egui::Color32::RED),
+    );
+    painter.circle_filled(center, 3.0, egui::Color32::RED);
+
+    response
+}
+
+/// 把角度换算到 (-180, 180] 范围内
+fn normalize_degrees(degrees: f32) -> f32 {
+    let degrees = degrees.rem_euclid(360.0);
+    if degrees > 180.0 {
+        degrees - 360.0
+    } else {
+        degrees
+    }
+}
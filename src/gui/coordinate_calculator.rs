@@ -69,6 +69,10 @@ impl CoordinateCalculator {
             enable_color_variation: false,
             base_hue: 0.0,
             text_direction: TextDirection::Right,
+            text_angle: 0.0,
+            mirror: false,
+            effects: Default::default(),
+            font: None,
         }
     }
 }
@@ -2,17 +2,21 @@ pub mod control_panel;
 pub mod coordinate_calculator;
 pub mod image_display;
 pub mod selection_handler;
+pub mod skeleton_preview;
 
 pub use control_panel::ControlPanel;
 pub use coordinate_calculator::CoordinateCalculator;
 pub use image_display::ImageDisplay;
 pub use selection_handler::SelectionHandler;
+pub use skeleton_preview::{PreviewSource, SkeletonPreview};
 
+use crate::font::FontChoice;
+use crate::image_processor::TextEffects;
 use anyhow::Result;
 use eframe::egui;
 use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum TextDirection {
     Up,    // Up
     Down,  // Down
@@ -29,9 +33,29 @@ impl TextDirection {
             TextDirection::Right => "Right",
         }
     }
+
+    /// 文字相对于正常方向顺时针旋转的角度
+    pub fn clockwise_degrees(&self) -> u32 {
+        match self {
+            TextDirection::Down => 0,
+            TextDirection::Left => 90,
+            TextDirection::Up => 180,
+            TextDirection::Right => 270,
+        }
+    }
+
+    /// 区域在纸张上逆时针旋转 degrees 度存放时，原始坐标中的文字方向在纸张上对应的方向
+    pub fn rotated_ccw(&self, degrees: u32) -> TextDirection {
+        match (self.clockwise_degrees() + 360 - degrees % 360) % 360 {
+            90 => TextDirection::Left,
+            180 => TextDirection::Up,
+            270 => TextDirection::Right,
+            _ => TextDirection::Down,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -41,6 +65,14 @@ pub struct Rect {
     pub enable_color_variation: bool,
     pub base_hue: f32,
     pub text_direction: TextDirection,
+    /// 在朝向的基础上额外顺时针旋转的角度（度）
+    pub text_angle: f32,
+    /// 文字左右镜像（在旋转之前进行）
+    pub mirror: bool,
+    /// 描边和投影
+    pub effects: TextEffects,
+    /// 字体文件，None 表示使用系统字体
+    pub font: Option<FontChoice>,
 }
 
 pub struct RectSelector {
@@ -48,12 +80,18 @@ pub struct RectSelector {
     selection_handler: SelectionHandler,
     coordinate_calculator: CoordinateCalculator,
     control_panel: ControlPanel,
+    skeleton_preview: Option<SkeletonPreview>,
     selected_rect: Arc<Mutex<Option<Rect>>>,
     image_path: String,
 }
 
 impl RectSelector {
-    pub fn select_rect(image_path: &str) -> Result<Rect> {
+    /// 打开选择窗口；提供 preview 时在右侧显示带文字的初始姿势预览，font 为初始选中的字体
+    pub fn select_rect(
+        image_path: &str,
+        preview: Option<PreviewSource>,
+        font: Option<FontChoice>,
+    ) -> Result<Rect> {
         let options = eframe::NativeOptions {
             viewport: egui::ViewportBuilder::default().with_inner_size([1200.0, 1000.0]),
             ..Default::default()
@@ -63,11 +101,15 @@ impl RectSelector {
         let selected_rect_clone = selected_rect.clone();
         let image_path = image_path.to_string();
 
+        let mut selection_handler = SelectionHandler::new(selected_rect_clone.clone());
+        selection_handler.set_font(font.clone());
+
         let selector = RectSelector {
             image_display: ImageDisplay::new(),
-            selection_handler: SelectionHandler::new(selected_rect_clone.clone()),
+            selection_handler,
             coordinate_calculator: CoordinateCalculator::new(),
-            control_panel: ControlPanel::new(),
+            control_panel: ControlPanel::new(font.as_ref()),
+            skeleton_preview: preview.map(|preview| SkeletonPreview::new(preview, font.clone())),
             selected_rect: selected_rect_clone.clone(),
             image_path,
         };
@@ -102,7 +144,16 @@ impl eframe::App for RectSelector {
             &self.image_display,
         );
 
-        // 右侧面板 - 图片显示区域
+        // 右侧面板 - 初始姿势预览
+        if let Some(skeleton_preview) = &mut self.skeleton_preview {
+            skeleton_preview.show(
+                ctx,
+                &self.selection_handler,
+                self.image_display.get_image_size(),
+            );
+        }
+
+        // 中间面板 - 图片显示区域
         egui::CentralPanel::default().show(ctx, |ui| {
             self.image_display
                 .show(ui, &mut self.selection_handler, &self.coordinate_calculator);
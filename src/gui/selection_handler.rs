@@ -1,4 +1,6 @@
+use crate::font::FontChoice;
 use crate::gui::{CoordinateCalculator, Rect, TextDirection};
+use crate::image_processor::{Outline, Shadow, TextEffects};
 use eframe::egui;
 use std::sync::{Arc, Mutex};
 
@@ -13,6 +15,13 @@ pub struct SelectionHandler {
     enable_selection: bool,
     enable_color_variation: bool,
     text_direction: TextDirection,
+    text_angle: f32,
+    mirror: bool,
+    enable_outline: bool,
+    outline: Outline,
+    enable_shadow: bool,
+    shadow: Shadow,
+    font: Option<FontChoice>,
 }
 
 impl SelectionHandler {
@@ -27,6 +36,13 @@ impl SelectionHandler {
             enable_selection: true,
             enable_color_variation: true,
             text_direction: TextDirection::Right,
+            text_angle: 0.0,
+            mirror: false,
+            enable_outline: false,
+            outline: Outline::default(),
+            enable_shadow: false,
+            shadow: Shadow::default(),
+            font: None,
         }
     }
 
@@ -114,6 +130,10 @@ impl SelectionHandler {
                     enable_color_variation: self.enable_color_variation,
                     base_hue: 0.0, // 不再使用，设为0.0
                     text_direction: self.text_direction,
+                    text_angle: self.text_angle,
+                    mirror: self.mirror,
+                    effects: self.get_text_effects(),
+                    font: self.font.clone(),
                 };
                 *self.selected_rect.lock().unwrap() = Some(selected_rect);
             }
@@ -128,6 +148,10 @@ impl SelectionHandler {
                 enable_color_variation: self.enable_color_variation,
                 base_hue: 0.0, // 不再使用，设为0.0
                 text_direction: self.text_direction,
+                text_angle: self.text_angle,
+                mirror: self.mirror,
+                effects: self.get_text_effects(),
+                font: self.font.clone(),
             };
             *self.selected_rect.lock().unwrap() = Some(default_rect);
         }
@@ -220,4 +244,68 @@ impl SelectionHandler {
     pub fn get_text_direction(&self) -> TextDirection {
         self.text_direction
     }
+
+    pub fn set_text_angle(&mut self, angle: f32) {
+        self.text_angle = angle;
+    }
+
+    pub fn get_text_angle(&self) -> f32 {
+        self.text_angle
+    }
+
+    pub fn set_mirror(&mut self, mirror: bool) {
+        self.mirror = mirror;
+    }
+
+    pub fn get_mirror(&self) -> bool {
+        self.mirror
+    }
+
+    pub fn set_enable_outline(&mut self, enable: bool) {
+        self.enable_outline = enable;
+    }
+
+    pub fn get_enable_outline(&self) -> bool {
+        self.enable_outline
+    }
+
+    pub fn set_outline(&mut self, outline: Outline) {
+        self.outline = outline;
+    }
+
+    pub fn get_outline(&self) -> Outline {
+        self.outline
+    }
+
+    pub fn set_enable_shadow(&mut self, enable: bool) {
+        self.enable_shadow = enable;
+    }
+
+    pub fn get_enable_shadow(&self) -> bool {
+        self.enable_shadow
+    }
+
+    pub fn set_shadow(&mut self, shadow: Shadow) {
+        self.shadow = shadow;
+    }
+
+    pub fn get_shadow(&self) -> Shadow {
+        self.shadow
+    }
+
+    pub fn set_font(&mut self, font: Option<FontChoice>) {
+        self.font = font;
+    }
+
+    pub fn get_font(&self) -> Option<&FontChoice> {
+        self.font.as_ref()
+    }
+
+    /// 当前启用的描边和投影
+    pub fn get_text_effects(&self) -> TextEffects {
+        TextEffects {
+            outline: self.enable_outline.then_some(self.outline),
+            shadow: self.enable_shadow.then_some(self.shadow),
+        }
+    }
 }
@@ -0,0 +1,173 @@
+use crate::atlas::Atlas;
+use crate::font::{FontChoice, FontStack, LoadedFont};
+use crate::gui::{Rect, SelectionHandler};
+use crate::image_processor::ImageProcessor;
+use crate::preview::SetupPose;
+use eframe::egui;
+use image::RgbaImage;
+use std::collections::HashMap;
+
+/// 预览图的最大边长
+const PREVIEW_SIZE: u32 = 512;
+
+/// 按选择的矩形设置 ImageProcessor 的裁剪范围和网格遮罩
+pub type ApplyClip = Box<dyn Fn(&Rect, &mut ImageProcessor) -> anyhow::Result<()>>;
+
+/// 初始姿势预览需要的数据
+pub struct PreviewSource {
+    pub pose: SetupPose,
+    pub atlas: Atlas,
+    /// 以纸张名为键的纸张图片
+    pub pages: HashMap<String, RgbaImage>,
+    /// 绘制文字的纸张名
+    pub label_page: String,
+    pub image_processor: ImageProcessor,
+    /// image_processor 使用的字体链，GUI 中换字体时只替换其中的主字体
+    pub fonts: FontStack,
+    /// 预览中使用的文字，例如第一个变体的编号
+    pub label_text: String,
+    /// 按选择的矩形设置 image_processor 的裁剪范围和网格遮罩，与生成时相同
+    pub apply_clip: ApplyClip,
+}
+
+/// 右侧的初始姿势预览面板，当前选择的文字会被绘制到纸张上再组装
+pub struct SkeletonPreview {
+    source: PreviewSource,
+    texture: Option<egui::TextureHandle>,
+    rendered_for: Option<Rect>,
+    /// 字体链当前的主字体，None 表示系统字体
+    font: Option<FontChoice>,
+    /// 文字无法绘制的原因，例如缺字
+    error: Option<String>,
+}
+
+impl SkeletonPreview {
+    /// font 是 source 的字体链中主字体对应的选择
+    pub fn new(source: PreviewSource, font: Option<FontChoice>) -> Self {
+        Self {
+            source,
+            texture: None,
+            rendered_for: None,
+            font,
+            error: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        selection_handler: &SelectionHandler,
+        image_size: egui::Vec2,
+    ) {
+        egui::SidePanel::right("skeleton_preview")
+            .resizable(true)
+            .default_width(360.0)
+            .min_width(200.0)
+            .show(ctx, |ui| {
+                ui.heading("Setup Pose Preview");
+                ui.add_space(10.0);
+
+                // 拖拽过程中不重新绘制，松开后再更新
+                if !selection_handler.is_selecting() {
+                    self.update_texture(ctx, selection_handler, image_size);
+                }
+
+                match &self.texture {
+                    Some(texture) => {
+                        ui.add(egui::Image::new(texture).max_width(ui.available_width()));
+                    }
+                    None => {
+                        ui.label("Rendering preview...");
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.add_space(5.0);
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        format!("The label cannot be drawn: {}", error),
+                    );
+                }
+
+                if self.source.pose.skipped > 0 {
+                    ui.add_space(5.0);
+                    ui.label(format!(
+                        "{} mesh or other non-region attachments are not shown",
+                        self.source.pose.skipped
+                    ));
+                }
+            });
+    }
+
+    fn update_texture(
+        &mut self,
+        ctx: &egui::Context,
+        selection_handler: &SelectionHandler,
+        image_size: egui::Vec2,
+    ) {
+        let label =
+            selection_handler
+                .get_selection_info(image_size)
+                .map(|(x, y, width, height)| Rect {
+                    x: x as i32,
+                    y: y as i32,
+                    width: width as u32,
+                    height: height as u32,
+                    text_color: selection_handler.get_text_color(),
+                    enable_color_variation: false,
+                    base_hue: 0.0,
+                    text_direction: selection_handler.get_text_direction(),
+                    text_angle: selection_handler.get_text_angle(),
+                    mirror: selection_handler.get_mirror(),
+                    effects: selection_handler.get_text_effects(),
+                    font: selection_handler.get_font().cloned(),
+                });
+        if self.texture.is_some() && label == self.rendered_for {
+            return;
+        }
+
+        // 字体改变时重新加载；控制面板已检查过字体，加载失败时沿用之前的字体
+        let source = &mut self.source;
+        if let Some(rect) = &label {
+            if rect.font != self.font {
+                if let Ok(loaded) = LoadedFont::load_or_system(rect.font.as_ref()) {
+                    source.fonts.set_primary(loaded);
+                    source.image_processor.set_fonts(source.fonts.clone());
+                    self.font = rect.font.clone();
+                }
+            }
+        }
+
+        // 临时把文字纸张换成画好文字的版本，绘制后再换回
+        let labelled = label.as_ref().map(|rect| {
+            (source.apply_clip)(rect, &mut source.image_processor)?;
+            source
+                .image_processor
+                .render_text_in_rect(&source.label_text, rect, false, 0.0, 1)
+                .map(|(image, _)| image)
+        });
+        self.error = match &labelled {
+            Some(Err(error)) => Some(format!("{:#}", error)),
+            _ => None,
+        };
+        let labelled = labelled.and_then(Result::ok);
+        let original = match labelled {
+            Some(image) => source.pages.insert(source.label_page.clone(), image),
+            None => None,
+        };
+
+        let rendered = source
+            .pose
+            .render(&source.atlas, &source.pages, PREVIEW_SIZE);
+
+        if let Some(original) = original {
+            source.pages.insert(source.label_page.clone(), original);
+        }
+
+        let size = [rendered.width() as usize, rendered.height() as usize];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, rendered.as_raw());
+        self.texture =
+            Some(ctx.load_texture("setup_pose_preview", color_image, Default::default()));
+        self.rendered_for = label;
+    }
+}
@@ -1,12 +1,213 @@
-use crate::gui::{Rect, TextDirection};
+use crate::font::FontStack;
+use crate::geometry::{PixelMask, PixelRect};
+use crate::gui::Rect;
 use anyhow::Result;
 use image::{DynamicImage, Rgba, RgbaImage};
-use imageproc::drawing::draw_text_mut;
-use rusttype::{Font, Scale};
-use std::path::Path;
+use rusttype::{Font, PositionedGlyph, Scale};
+
+/// 预乘透明度（premultiplied alpha）模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PmaMode {
+    /// 根据图集的 pma 字段或图片像素自动判断
+    Auto,
+    /// 图片是预乘透明度的
+    On,
+    /// 图片是普通（直通）透明度的
+    Off,
+}
+
+/// 颜色变化的实现方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VariationMode {
+    /// 直接修改贴图的每个像素，每个变体都需要自己的 PNG
+    Texture,
+    /// 把颜色写入 JSON 骨骼的插槽 color（以及可选的 dark），贴图保持不变
+    Slot,
+}
+
+/// 文字块在垂直于书写方向上以什么范围对齐；书写方向上总是以字形的实际像素对齐
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TextAnchor {
+    /// 字形实际像素的包围盒
+    Ink,
+    /// 第一行的 ascent 到最后一行的 descent，基线位置只取决于字号
+    Baseline,
+    /// 第一行大写字母顶端到最后一行的基线，数字和大写字母看起来居中且基线一致
+    CapHeight,
+}
+
+/// 书写方向上的对齐：各行之间的对齐，以及文字块在矩形中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// 行堆叠方向上文字块在矩形中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// 文字与矩形四边之间保留的像素，按纹理上的上、右、下、左计算，不随文字旋转
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextPadding {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl TextPadding {
+    /// 与 CSS 相同：`4` 用于四边，`4,8` 为上下和左右，`1,2,3,4` 为上、右、下、左
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let values = value
+            .split(',')
+            .map(|part| {
+                part.trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid padding: {}", value))
+            })
+            .collect::<Result<Vec<u32>, String>>()?;
+        match values[..] {
+            [all] => Ok(Self {
+                top: all,
+                right: all,
+                bottom: all,
+                left: all,
+            }),
+            [vertical, horizontal] => Ok(Self {
+                top: vertical,
+                right: horizontal,
+                bottom: vertical,
+                left: horizontal,
+            }),
+            [top, right, bottom, left] => Ok(Self {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            _ => Err("expected 1, 2 or 4 comma separated values".to_string()),
+        }
+    }
+}
+
+/// 文字的排版方式：多行、换行、对齐和内边距
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    pub padding: TextPadding,
+    pub anchor: TextAnchor,
+    /// 行距，字体行高（ascent 到 descent 加 line gap）的倍数
+    pub line_spacing: f32,
+    pub horizontal_align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+    /// 一行放不下时在空白处自动换行
+    pub word_wrap: bool,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            padding: TextPadding::default(),
+            anchor: TextAnchor::Ink,
+            line_spacing: 1.0,
+            horizontal_align: HorizontalAlign::Center,
+            vertical_align: VerticalAlign::Middle,
+            word_wrap: false,
+        }
+    }
+}
+
+/// 描边拐角的形状
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutlineJoin {
+    Round,
+    Miter,
+    Bevel,
+}
+
+impl OutlineJoin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutlineJoin::Round => "Round",
+            OutlineJoin::Miter => "Miter",
+            OutlineJoin::Bevel => "Bevel",
+        }
+    }
+}
+
+/// 文字描边
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outline {
+    /// 描边宽度（像素），向字形外侧扩展
+    pub width: f32,
+    pub color: egui::Color32,
+    pub join: OutlineJoin,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Self {
+            width: 2.0,
+            color: egui::Color32::WHITE,
+            join: OutlineJoin::Round,
+        }
+    }
+}
+
+/// 文字投影
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    /// 投影在纹理上的偏移（像素），不随文字旋转
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// 模糊半径（像素），0 为硬边投影
+    pub blur: f32,
+    pub color: egui::Color32,
+    /// 不透明度，0 到 1
+    pub opacity: f32,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset_x: 2.0,
+            offset_y: 2.0,
+            blur: 2.0,
+            color: egui::Color32::BLACK,
+            opacity: 0.6,
+        }
+    }
+}
+
+/// 文字的描边和投影，绘制时先于文字本身合成
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextEffects {
+    pub outline: Option<Outline>,
+    pub shadow: Option<Shadow>,
+}
+
+/// 单次绘制的结果
+#[derive(Debug, Clone, Default)]
+pub struct DrawReport {
+    /// 落在裁剪区域之外、被丢弃的文字像素数量
+    pub clipped_pixels: u64,
+    /// 保留下来、但落在网格可见三角形之外的文字像素数量（游戏中看不到）
+    pub hidden_pixels: u64,
+}
 
 pub struct ImageProcessor {
     original_image: DynamicImage,
+    clip_bounds: Option<PixelRect>,
+    visible_mask: Option<PixelMask>,
+    clip_to_mask: bool,
+    premultiplied: bool,
+    text_layout: TextLayout,
+    fonts: Option<FontStack>,
 }
 
 impl ImageProcessor {
@@ -14,10 +215,65 @@ impl ImageProcessor {
         let img = image::open(image_path).expect("无法打开图片");
         Self {
             original_image: img,
+            clip_bounds: None,
+            visible_mask: None,
+            clip_to_mask: false,
+            premultiplied: false,
+            text_layout: TextLayout::default(),
+            fonts: None,
+        }
+    }
+
+    /// 设置图片是否为预乘透明度；为 true 时绘制前先反预乘，保存前再预乘回去
+    pub fn set_premultiplied(&mut self, premultiplied: bool) {
+        self.premultiplied = premultiplied;
+    }
+
+    /// 根据像素判断图片是否像是预乘透明度的：存在半透明像素，且所有像素的颜色分量都不超过 alpha
+    pub fn looks_premultiplied(&self) -> bool {
+        let rgba_img = self.original_image.to_rgba8();
+        let mut has_translucent = false;
+
+        for pixel in rgba_img.pixels() {
+            let alpha = pixel[3];
+            if pixel[0] > alpha || pixel[1] > alpha || pixel[2] > alpha {
+                return false;
+            }
+            if alpha < 255 && alpha > 0 {
+                has_translucent = true;
+            }
         }
+
+        has_translucent
+    }
+
+    /// 设置多行、换行、对齐和内边距；字号按扣除内边距后的范围计算
+    pub fn set_text_layout(&mut self, text_layout: TextLayout) {
+        self.text_layout = text_layout;
     }
 
-    pub fn draw_text_in_rect(&self, output_path: &str, text: &str, rect: &Rect) -> Result<()> {
+    /// 设置绘制文字所用的字体链
+    pub fn set_fonts(&mut self, fonts: FontStack) {
+        self.fonts = Some(fonts);
+    }
+
+    /// 设置额外的裁剪范围（例如图集区域减去内边距），绘制始终会被限制在文字矩形内
+    pub fn set_clip_bounds(&mut self, clip_bounds: Option<PixelRect>) {
+        self.clip_bounds = clip_bounds;
+    }
+
+    /// 设置网格在纸张上实际可见的像素；clip 为 true 时不可见处的文字像素会被丢弃
+    pub fn set_visible_mask(&mut self, mask: Option<PixelMask>, clip: bool) {
+        self.visible_mask = mask;
+        self.clip_to_mask = clip;
+    }
+
+    pub fn draw_text_in_rect(
+        &self,
+        output_path: &str,
+        text: &str,
+        rect: &Rect,
+    ) -> Result<DrawReport> {
         self.draw_text_in_rect_with_color_variation(output_path, text, rect, false, 0.0, 0)
     }
 
@@ -29,94 +285,314 @@ impl ImageProcessor {
         enable_color_variation: bool,
         base_hue: f32,
         index: u32,
-    ) -> Result<()> {
+    ) -> Result<DrawReport> {
+        let (rgba_img, report) =
+            self.render_text_in_rect(text, rect, enable_color_variation, base_hue, index)?;
+
+        // 保存图片
+        let final_img = DynamicImage::ImageRgba8(rgba_img);
+        final_img.save(output_path)?;
+
+        Ok(report)
+    }
+
+    /// 在内存中绘制文字并返回整张图片，透明度形式与原图一致（预乘的图片返回预乘结果）
+    pub fn render_text_in_rect(
+        &self,
+        text: &str,
+        rect: &Rect,
+        enable_color_variation: bool,
+        base_hue: f32,
+        index: u32,
+    ) -> Result<(RgbaImage, DrawReport)> {
         // 克隆原始图片
         let img = self.original_image.clone();
 
         // 转换为RGBA格式以便绘制
         let mut rgba_img = img.to_rgba8();
 
-        // 计算文字大小，使其适合矩形区域
-        let font_size = self.calculate_font_size(text, rect.width, rect.height);
+        // 预乘透明度的图片先还原为直通透明度，文字绘制和颜色变化都在直通颜色上进行
+        if self.premultiplied {
+            unpremultiply(&mut rgba_img);
+        }
 
-        // 加载系统字体
-        let font = self
-            .load_system_font()
-            .ok_or_else(|| anyhow::anyhow!("Failed to load font"))?;
+        let fonts = self.fonts_for(text)?;
 
-        // 根据文字朝向绘制文字
-        self.draw_text_with_direction(&mut rgba_img, text, &font, font_size, rect, rect.text_color);
+        // 计算文字大小，使其适合矩形区域
+        let font_size = self.calculate_font_size(text, fonts, rect);
+
+        // 根据文字朝向绘制文字，之后把裁剪区域外被改动的像素还原
+        let untouched = rgba_img.clone();
+        self.draw_text_with_direction(&mut rgba_img, text, fonts, font_size, rect, rect.text_color);
+        let report = self.restore_outside_clip(&mut rgba_img, &untouched, rect);
 
         // 应用颜色变化
         if enable_color_variation {
             self.apply_color_variation(&mut rgba_img, base_hue, index);
         }
 
-        // 保存图片
-        let final_img = DynamicImage::ImageRgba8(rgba_img);
-        final_img.save(output_path)?;
+        if self.premultiplied {
+            premultiply(&mut rgba_img);
+        }
 
-        Ok(())
+        Ok((rgba_img, report))
     }
 
-    fn calculate_font_size(&self, text: &str, rect_width: u32, rect_height: u32) -> f32 {
-        // 根据矩形大小和文字长度计算合适的字体大小
-        let text_len = text.len() as f32;
-        let width_ratio = rect_width as f32 / (text_len * 0.6); // 假设每个字符宽度约为字体大小的0.6倍
-        let height_ratio = rect_height as f32 * 0.8; // 使用矩形高度的80%
+    /// 在透明底上单独绘制文字，图片大小与文字矩形相同，原图像素保持不变
+    ///
+    /// 返回的透明度形式与原图一致；颜色变化只作用于文字本身。
+    pub fn render_label(
+        &self,
+        text: &str,
+        rect: &Rect,
+        enable_color_variation: bool,
+        index: u32,
+    ) -> Result<RgbaImage> {
+        let fonts = self.fonts_for(text)?;
+        let font_size = self.calculate_font_size(text, fonts, rect);
+
+        let mut label = RgbaImage::new(rect.width, rect.height);
+        let label_rect = Rect {
+            x: 0,
+            y: 0,
+            ..rect.clone()
+        };
+        self.draw_text_with_direction(
+            &mut label,
+            text,
+            fonts,
+            font_size,
+            &label_rect,
+            rect.text_color,
+        );
+
+        // 透明底上按 source-over 混合得到的已是直通透明度
+        if enable_color_variation {
+            self.apply_color_variation(&mut label, 0.0, index);
+        }
+        if self.premultiplied {
+            premultiply(&mut label);
+        }
 
-        // 取较小的值作为字体大小，确保文字能完全放入矩形内
-        let font_size = width_ratio.min(height_ratio);
+        Ok(label)
+    }
 
-        // 限制字体大小范围
-        font_size.max(12.0).min(200.0)
+    /// 计算实际允许绘制的范围：文字矩形 ∩ 图片范围 ∩ 额外裁剪范围
+    fn clip_rect(&self, rect: &Rect, image_width: u32, image_height: u32) -> Option<PixelRect> {
+        let label_bounds = PixelRect::new(rect.x, rect.y, rect.width, rect.height);
+        let image_bounds = PixelRect::new(0, 0, image_width, image_height);
+        let clip = label_bounds.intersect(&image_bounds)?;
+        match &self.clip_bounds {
+            Some(clip_bounds) => clip.intersect(clip_bounds),
+            None => Some(clip),
+        }
     }
 
-    fn measure_text_width(&self, text: &str, font: &Font, scale: Scale) -> f32 {
-        let mut width: f32 = 0.0;
-        for glyph in font.layout(text, scale, rusttype::point(0.0, 0.0)) {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                width = width.max(bounding_box.max.x as f32);
+    fn restore_outside_clip(
+        &self,
+        rgba_img: &mut RgbaImage,
+        untouched: &RgbaImage,
+        rect: &Rect,
+    ) -> DrawReport {
+        let clip = self.clip_rect(rect, rgba_img.width(), rgba_img.height());
+        let mut report = DrawReport::default();
+
+        for (x, y, pixel) in rgba_img.enumerate_pixels_mut() {
+            let original = untouched.get_pixel(x, y);
+            if pixel == original {
+                continue;
+            }
+
+            let (x, y) = (x as i32, y as i32);
+            let inside = clip.is_some_and(|clip| clip.contains(x, y));
+            let visible = self
+                .visible_mask
+                .as_ref()
+                .map_or(true, |mask| mask.contains(x, y));
+
+            if !inside || (self.clip_to_mask && !visible) {
+                *pixel = *original;
+                report.clipped_pixels += 1;
+            } else if !visible {
+                report.hidden_pixels += 1;
             }
         }
-        width
-    }
-
-    fn load_system_font(&self) -> Option<Font<'static>> {
-        // 尝试加载系统字体
-        // 这里可以添加更多系统字体路径
-        let system_fonts = [
-            "/System/Library/Fonts/Arial.ttf",                 // macOS
-            "/System/Library/Fonts/Helvetica.ttc",             // macOS
-            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf", // Linux
-            "C:\\Windows\\Fonts\\arial.ttf",                   // Windows
-        ];
-
-        for font_path in &system_fonts {
-            if Path::new(font_path).exists() {
-                if let Ok(font_data) = std::fs::read(font_path) {
-                    // 将字体数据转换为静态生命周期
-                    let font_data = Box::leak(font_data.into_boxed_slice());
-                    if let Some(font) = Font::try_from_bytes(font_data) {
-                        return Some(font);
-                    }
+
+        report
+    }
+
+    /// 绘制 text 所用的字体链；有字符在所有字体中都找不到时报错
+    fn fonts_for(&self, text: &str) -> Result<&FontStack> {
+        let fonts = self
+            .fonts
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Failed to load font"))?;
+        fonts.check_glyphs(text)?;
+        Ok(fonts)
+    }
+
+    /// 二分查找能让文字实际字形放进矩形（减去内边距）的最大字号
+    ///
+    /// 每个字号都重新换行排版，比较的是整个文字块的字形包围盒按文字角度旋转后的外接矩形，
+    /// 竖排时相当于宽高互换。字号下限为1像素，连1像素都放不下时超出的部分交给裁剪处理。
+    fn calculate_font_size(&self, text: &str, fonts: &FontStack, rect: &Rect) -> f32 {
+        let (available_width, available_height) = self.available_size(rect);
+        let (sin, cos) = text_rotation(rect).sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let outline_width = rect
+            .effects
+            .outline
+            .map_or(0.0, |outline| outline.width.max(0.0));
+        let wrap_width = self.wrap_width(rect, outline_width);
+
+        let fits = |font_size: f32| {
+            let block = self.layout_block(text, fonts, Scale::uniform(font_size), wrap_width);
+            match glyph_bounds(&block.glyphs) {
+                Some(bounds) => {
+                    // 描边向四周扩展，也要放进矩形
+                    let (width, height) = (
+                        bounds.width() as f32 + outline_width * 2.0,
+                        bounds.height() as f32 + outline_width * 2.0,
+                    );
+                    // 容差吸收 90 度整数倍时三角函数的舍入误差
+                    width * cos + height * sin <= available_width + 1e-3
+                        && width * sin + height * cos <= available_height + 1e-3
                 }
+                None => true,
+            }
+        };
+
+        // 字形的墨迹总是小于字号本身，上限取可用边长的数倍即可
+        let mut low = 1.0_f32;
+        let mut high = available_width.max(available_height).max(1.0) * 4.0;
+        if !fits(low) {
+            return low;
+        }
+        while high - low > 0.1 {
+            let middle = (low + high) / 2.0;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle;
             }
         }
+        low
+    }
 
-        None
+    /// 矩形扣除内边距后的宽高
+    fn available_size(&self, rect: &Rect) -> (f32, f32) {
+        let padding = self.text_layout.padding;
+        (
+            rect.width.saturating_sub(padding.left + padding.right) as f32,
+            rect.height.saturating_sub(padding.top + padding.bottom) as f32,
+        )
     }
 
-    fn apply_color_variation(&self, rgba_img: &mut image::RgbaImage, _base_hue: f32, index: u32) {
-        // 根据图片索引自动生成色调，均匀分布在360度色环上
-        // 使用黄金比例来获得更好的颜色分布
-        let golden_ratio = 1.618033988749895;
-        let current_hue = (index as f32 * 360.0 * golden_ratio) % 360.0;
+    /// 自动换行的行宽：文字方向更接近水平时取可用宽度，否则取可用高度，再减去两侧描边
+    fn wrap_width(&self, rect: &Rect, outline_width: f32) -> Option<f32> {
+        if !self.text_layout.word_wrap {
+            return None;
+        }
+        let (available_width, available_height) = self.available_size(rect);
+        let (sin, cos) = text_rotation(rect).sin_cos();
+        let length = if cos.abs() >= sin.abs() {
+            available_width
+        } else {
+            available_height
+        };
+        Some(length - outline_width * 2.0)
+    }
+
+    /// 文字坐标系中的排版框大小：文字方向是 90 度的整数倍时为可用范围（竖排时宽高互换）
+    /// 减去描边，其他角度没有与之对齐的边，排版框就是文字块本身，对齐只作用于各行之间
+    fn text_frame(
+        &self,
+        rect: &Rect,
+        outline_width: f32,
+        block_width: f32,
+        block_height: f32,
+    ) -> (f32, f32) {
+        let degrees =
+            (rect.text_direction.clockwise_degrees() as f32 + rect.text_angle).rem_euclid(360.0);
+        let quarter_turns = (degrees / 90.0).round();
+        if (degrees - quarter_turns * 90.0).abs() > 1e-3 {
+            return (block_width, block_height);
+        }
+
+        let (available_width, available_height) = self.available_size(rect);
+        let (width, height) = if quarter_turns as i32 % 2 == 1 {
+            (available_height, available_width)
+        } else {
+            (available_width, available_height)
+        };
+        (width - outline_width * 2.0, height - outline_width * 2.0)
+    }
+
+    /// 多行排版：按换行符分行，给出 wrap_width 时再在空白处自动换行，
+    /// 行距为字体行高乘以行距倍数，各行按水平对齐方式在文字块宽度内错开
+    fn layout_block(
+        &self,
+        text: &str,
+        fonts: &FontStack,
+        scale: Scale,
+        wrap_width: Option<f32>,
+    ) -> TextBlock {
+        let mut lines = Vec::new();
+        for line in text.split('\n') {
+            let line = line.trim_end_matches('\r');
+            match wrap_width {
+                Some(wrap_width) => lines.extend(wrap_line(line, fonts, scale, wrap_width)),
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        // 每行单独排版，记下墨迹在书写方向上的范围
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|line| {
+                let glyphs = fonts.layout(line, scale);
+                let extent = glyph_bounds(&glyphs).map(|bounds| (bounds.min.x, bounds.max.x));
+                (glyphs, extent)
+            })
+            .collect();
+        let width = lines
+            .iter()
+            .filter_map(|(_, extent)| extent.map(|(left, right)| (right - left) as f32))
+            .fold(0.0, f32::max);
+
+        let v_metrics = fonts.primary().font.v_metrics(scale);
+        let line_height = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap)
+            * self.text_layout.line_spacing;
+
+        let mut glyphs = Vec::new();
+        let mut baseline = 0.0;
+        for (index, (line_glyphs, extent)) in lines.into_iter().enumerate() {
+            baseline = index as f32 * line_height;
+            let Some((left, right)) = extent else {
+                continue;
+            };
+            let line_width = (right - left) as f32;
+            let shift = match self.text_layout.horizontal_align {
+                HorizontalAlign::Left => 0.0,
+                HorizontalAlign::Center => (width - line_width) / 2.0,
+                HorizontalAlign::Right => width - line_width,
+            } - left as f32;
+            for mut glyph in line_glyphs {
+                let position = glyph.position();
+                glyph.set_position(rusttype::point(position.x + shift, position.y + baseline));
+                glyphs.push(glyph);
+            }
+        }
 
-        // 将色调转换为弧度
-        let hue_rad = current_hue.to_radians();
+        TextBlock {
+            glyphs,
+            width,
+            last_baseline: baseline,
+        }
+    }
 
-        // 计算RGB通道的色调偏移
+    fn apply_color_variation(&self, rgba_img: &mut image::RgbaImage, _base_hue: f32, index: u32) {
+        let [r_shift, g_shift, b_shift] = variation_shifts(index);
 
         // 对每个像素应用颜色变化
         for pixel in rgba_img.pixels_mut() {
@@ -132,16 +608,10 @@ impl ImageProcessor {
                 continue;
             }
 
-            // 应用色调变化到RGB通道
-            // 使用不同的相位偏移来创建更丰富的颜色变化
-            let r_phase = hue_rad;
-            let g_phase = hue_rad + 2.0943951023931953; // +120度
-            let b_phase = hue_rad + 4.1887902047863905; // +240度
-
             // 计算新的RGB值，保持原始亮度
-            let new_r = (brightness + 0.3 * r_phase.cos()).clamp(0.0, 1.0);
-            let new_g = (brightness + 0.3 * g_phase.cos()).clamp(0.0, 1.0);
-            let new_b = (brightness + 0.3 * b_phase.cos()).clamp(0.0, 1.0);
+            let new_r = (brightness + r_shift).clamp(0.0, 1.0);
+            let new_g = (brightness + g_shift).clamp(0.0, 1.0);
+            let new_b = (brightness + b_shift).clamp(0.0, 1.0);
 
             // 应用颜色变化，但保持原始像素的透明度
             pixel[0] = (new_r * 255.0) as u8;
@@ -151,144 +621,455 @@ impl ImageProcessor {
         }
     }
 
+    /// 按朝向和角度把文字绘制到矩形中：沿书写方向以字形包围盒居中，垂直方向按对齐方式居中
+    ///
+    /// 文字先按水平方向绘制成覆盖率图，再（镜像后）绕对齐点旋转到矩形中心，
+    /// 任意角度共用同一个对齐点，旋转后不会偏移。
     fn draw_text_with_direction(
         &self,
         rgba_img: &mut RgbaImage,
         text: &str,
-        font: &Font,
+        fonts: &FontStack,
         font_size: f32,
         rect: &Rect,
         color: egui::Color32,
     ) {
         let scale = Scale::uniform(font_size);
-        let text_width = self.measure_text_width(text, font, scale);
-        let text_height = font.v_metrics(scale).ascent - font.v_metrics(scale).descent;
-
-        match rect.text_direction {
-            TextDirection::Down => {
-                // 向右（水平，正常方向）
-                let text_x = rect.x + (rect.width as i32 - text_width as i32) / 2;
-                let text_y = rect.y + (rect.height as i32 - text_height as i32) / 2;
-
-                draw_text_mut(
-                    rgba_img,
-                    Rgba([color.r(), color.g(), color.b(), 255]),
-                    text_x,
-                    text_y,
-                    scale,
-                    font,
-                    text,
-                );
-            }
-            TextDirection::Up => {
-                // 向左（水平，180度旋转）
-                let text_x = rect.x + (rect.width as i32 - text_width as i32) / 2;
-                let text_y = rect.y + (rect.height as i32 - text_height as i32) / 2;
+        let outline_width = rect
+            .effects
+            .outline
+            .map_or(0.0, |outline| outline.width.max(0.0));
+        let block = self.layout_block(text, fonts, scale, self.wrap_width(rect, outline_width));
+        let Some(ink) = glyph_bounds(&block.glyphs) else {
+            return;
+        };
 
-                self.draw_rotated_text(rgba_img, text, font, scale, text_x, text_y, 180.0, color);
+        // 覆盖率图四周留出描边和阴影模糊扩散的空间，原点是墨迹包围盒左上角向外 margin 处
+        let blur = rect
+            .effects
+            .shadow
+            .map_or(0.0, |shadow| shadow.blur.max(0.0));
+        let margin = (outline_width.ceil() + blur.ceil()) as i32 + 1;
+        let coverage_width = (ink.width() + margin * 2) as usize;
+        let coverage_height = (ink.height() + margin * 2) as usize;
+
+        let mut coverage = vec![0.0_f32; coverage_width * coverage_height];
+        for glyph in &block.glyphs {
+            let Some(bounds) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+            glyph.draw(|gx, gy, value| {
+                let x = (bounds.min.x + gx as i32 - ink.min.x + margin) as usize;
+                let y = (bounds.min.y + gy as i32 - ink.min.y + margin) as usize;
+                let cell = &mut coverage[y * coverage_width + x];
+                *cell = cell.max(value);
+            });
+        }
+
+        // 从下到上依次为阴影、描边、文字本身
+        let mut layers = Vec::new();
+        let outline = rect.effects.outline.map(|outline| {
+            let coverage = dilate_coverage(
+                &coverage,
+                coverage_width,
+                coverage_height,
+                outline_width,
+                outline.join,
+            );
+            (outline, coverage)
+        });
+        if let Some(shadow) = rect.effects.shadow {
+            // 阴影跟随描边后的外形
+            let shape = outline
+                .as_ref()
+                .map_or(&coverage, |(_, outline_coverage)| outline_coverage);
+            layers.push(TextLayer {
+                coverage: blur_coverage(shape, coverage_width, coverage_height, blur),
+                color: shadow.color,
+                opacity: shadow.opacity.clamp(0.0, 1.0),
+                offset: (shadow.offset_x, shadow.offset_y),
+            });
+        }
+        if let Some((outline, outline_coverage)) = outline {
+            layers.push(TextLayer {
+                coverage: outline_coverage,
+                color: outline.color,
+                opacity: 1.0,
+                offset: (0.0, 0.0),
+            });
+        }
+        layers.push(TextLayer {
+            coverage,
+            color,
+            opacity: 1.0,
+            offset: (0.0, 0.0),
+        });
+
+        // 文字块在垂直于书写方向上参与对齐的范围（第一行基线为 0）
+        let font = &fonts.primary().font;
+        let (top, bottom) = match self.text_layout.anchor {
+            TextAnchor::Ink => (ink.min.y as f32, ink.max.y as f32),
+            TextAnchor::Baseline => {
+                let v_metrics = font.v_metrics(scale);
+                (-v_metrics.ascent, block.last_baseline - v_metrics.descent)
             }
-            TextDirection::Right => {
-                // 向上（垂直，270度旋转）
-                let text_x = rect.x + (rect.width as i32 - text_height as i32) / 2;
-                let text_y = rect.y + (rect.height as i32 - text_width as i32) / 2;
+            TextAnchor::CapHeight => (-cap_height(font, scale), block.last_baseline),
+        };
+
+        // 文字块按对齐方式放进排版框，排版框的中心对准矩形扣除内边距后的中心；
+        // 对齐点即排版框中心在文字块坐标中的位置
+        let (frame_width, frame_height) =
+            self.text_frame(rect, outline_width, block.width, bottom - top);
+        let offset_x = match self.text_layout.horizontal_align {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => (frame_width - block.width) / 2.0,
+            HorizontalAlign::Right => frame_width - block.width,
+        };
+        let offset_y = match self.text_layout.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => (frame_height - (bottom - top)) / 2.0,
+            VerticalAlign::Bottom => frame_height - (bottom - top),
+        };
+        let anchor_x = frame_width / 2.0 - offset_x - (ink.min.x - margin) as f32;
+        let anchor_y = top + frame_height / 2.0 - offset_y - (ink.min.y - margin) as f32;
+
+        let padding = self.text_layout.padding;
+        let (available_width, available_height) = self.available_size(rect);
+        let center_x = (rect.x + padding.left as i32) as f32 + available_width / 2.0;
+        let center_y = (rect.y + padding.top as i32) as f32 + available_height / 2.0;
+        let (sin, cos) = text_rotation(rect).sin_cos();
+        let mirror = if rect.mirror { -1.0 } else { 1.0 };
 
-                self.draw_rotated_text(rgba_img, text, font, scale, text_x, text_y, 270.0, color);
+        // 覆盖率图的四个角经过镜像和旋转后在目标图片上的外接范围，再加上各图层的偏移
+        let corners = [
+            (0.0, 0.0),
+            (coverage_width as f32, 0.0),
+            (0.0, coverage_height as f32),
+            (coverage_width as f32, coverage_height as f32),
+        ]
+        .map(|(x, y)| {
+            let dx = (x - anchor_x) * mirror;
+            let dy = y - anchor_y;
+            (
+                center_x + dx * cos - dy * sin,
+                center_y + dx * sin + dy * cos,
+            )
+        });
+        let mut min_x = f32::MAX;
+        let mut max_x = f32::MIN;
+        let mut min_y = f32::MAX;
+        let mut max_y = f32::MIN;
+        for layer in &layers {
+            for (x, y) in corners {
+                min_x = min_x.min(x + layer.offset.0);
+                max_x = max_x.max(x + layer.offset.0);
+                min_y = min_y.min(y + layer.offset.1);
+                max_y = max_y.max(y + layer.offset.1);
             }
-            TextDirection::Left => {
-                // 向下（垂直，90度旋转）
-                let text_x = rect.x + (rect.width as i32 - text_height as i32) / 2;
-                let text_y = rect.y + (rect.height as i32 - text_width as i32) / 2;
+        }
+
+        // 双线性采样会向外扩散半个像素，范围多留一个像素
+        let start_x = (min_x.floor() as i32 - 1).max(0);
+        let start_y = (min_y.floor() as i32 - 1).max(0);
+        let end_x = (max_x.ceil() as i32 + 1).min(rgba_img.width() as i32);
+        let end_y = (max_y.ceil() as i32 + 1).min(rgba_img.height() as i32);
+        let tint = [1.0, 1.0, 1.0, 1.0];
+
+        // 逆向映射：目标像素中心经过反向旋转和镜像后，在覆盖率图中双线性取值，不会留下空洞
+        for target_y in start_y..end_y {
+            for target_x in start_x..end_x {
+                let pixel = rgba_img.get_pixel_mut(target_x as u32, target_y as u32);
+
+                for layer in &layers {
+                    // 图层偏移（阴影）在目标图片上计算，不随文字旋转
+                    let qx = target_x as f32 + 0.5 - center_x - layer.offset.0;
+                    let qy = target_y as f32 + 0.5 - center_y - layer.offset.1;
+                    let source_x = (qx * cos + qy * sin) * mirror + anchor_x;
+                    let source_y = -qx * sin + qy * cos + anchor_y;
 
-                self.draw_rotated_text(rgba_img, text, font, scale, text_x, text_y, 90.0, color);
+                    let value = sample_coverage(
+                        &layer.coverage,
+                        coverage_width,
+                        coverage_height,
+                        source_x,
+                        source_y,
+                    ) * layer.opacity;
+                    if value <= 0.0 {
+                        continue;
+                    }
+
+                    // 覆盖率作为图层的透明度，按 source-over 叠加到纹理上，保留字形的抗锯齿边缘
+                    let source = Rgba([
+                        layer.color.r(),
+                        layer.color.g(),
+                        layer.color.b(),
+                        (value * 255.0).round() as u8,
+                    ]);
+                    blend_over(pixel, &source, &tint);
+                }
             }
         }
     }
+}
 
-    fn draw_rotated_text(
-        &self,
-        rgba_img: &mut RgbaImage,
-        text: &str,
-        font: &Font,
-        scale: Scale,
-        center_x: i32,
-        center_y: i32,
-        angle_degrees: f32,
-        color: egui::Color32,
-    ) {
-        // 创建一个临时图片来绘制文字
-        let text_width = self.measure_text_width(text, font, scale) as u32;
-        let text_height = (font.v_metrics(scale).ascent - font.v_metrics(scale).descent) as u32;
-
-        // 添加一些边距
-        let margin = 10;
-        let temp_width = text_width + margin * 2;
-        let temp_height = text_height + margin * 2;
-
-        let mut temp_img = RgbaImage::new(temp_width, temp_height);
-
-        // 在临时图片上绘制文字
-        draw_text_mut(
-            &mut temp_img,
-            Rgba([color.r(), color.g(), color.b(), 255]),
-            margin as i32,
-            margin as i32,
-            scale,
-            font,
-            text,
-        );
+/// 排好的多行文字：字形坐标以第一行基线的起点为原点，各行已按对齐方式水平错开
+struct TextBlock {
+    glyphs: Vec<PositionedGlyph<'static>>,
+    /// 最宽一行的墨迹宽度，各行的墨迹都在 0 到 width 之间
+    width: f32,
+    /// 最后一行基线的 y 坐标
+    last_baseline: f32,
+}
+
+/// 文字的一个绘制图层：与文字同样旋转的覆盖率图、颜色、不透明度和在目标图片上的偏移
+struct TextLayer {
+    coverage: Vec<f32>,
+    color: egui::Color32,
+    opacity: f32,
+    offset: (f32, f32),
+}
+
+/// 文字相对于正常水平方向顺时针旋转的弧度：朝向对应的角度加上额外角度
+fn text_rotation(rect: &Rect) -> f32 {
+    (rect.text_direction.clockwise_degrees() as f32 + rect.text_angle).to_radians()
+}
 
-        // 将临时图片旋转并复制到目标图片
-        self.copy_rotated_image(rgba_img, &temp_img, center_x, center_y, angle_degrees);
+/// 按描边宽度扩张覆盖率图：圆角用圆形、尖角用正方形、斜角用菱形作为结构元素
+fn dilate_coverage(
+    coverage: &[f32],
+    width: usize,
+    height: usize,
+    radius: f32,
+    join: OutlineJoin,
+) -> Vec<f32> {
+    let reach = radius.ceil() as i32 + 1;
+    let mut kernel = Vec::new();
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            let (x, y) = (dx as f32, dy as f32);
+            let distance = match join {
+                OutlineJoin::Round => (x * x + y * y).sqrt(),
+                OutlineJoin::Miter => x.abs().max(y.abs()),
+                OutlineJoin::Bevel => x.abs() + y.abs(),
+            };
+            // 结构元素边缘按距离做抗锯齿
+            let weight = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            if weight > 0.0 {
+                kernel.push((dx, dy, weight));
+            }
+        }
     }
 
-    fn copy_rotated_image(
-        &self,
-        target: &mut RgbaImage,
-        source: &RgbaImage,
-        center_x: i32,
-        center_y: i32,
-        angle_degrees: f32,
-    ) {
-        let angle_rad = angle_degrees.to_radians();
-        let cos_angle = angle_rad.cos();
-        let sin_angle = angle_rad.sin();
-
-        let source_width = source.width() as i32;
-        let source_height = source.height() as i32;
-        let target_width = target.width() as i32;
-        let target_height = target.height() as i32;
-
-        // 计算旋转后的边界
-        let half_width = source_width / 2;
-        let half_height = source_height / 2;
-
-        for y in 0..source_height {
-            for x in 0..source_width {
-                // 相对于中心的坐标
-                let rel_x = x - half_width;
-                let rel_y = y - half_height;
-
-                // 应用旋转变换
-                let new_x = (rel_x as f32 * cos_angle - rel_y as f32 * sin_angle) as i32;
-                let new_y = (rel_x as f32 * sin_angle + rel_y as f32 * cos_angle) as i32;
-
-                // 计算在目标图片中的位置
-                let target_x = center_x + new_x;
-                let target_y = center_y + new_y;
-
-                // 检查边界
-                if target_x >= 0
-                    && target_x < target_width
-                    && target_y >= 0
-                    && target_y < target_height
+    let mut output = vec![0.0_f32; coverage.len()];
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let mut value = 0.0_f32;
+            for &(dx, dy, weight) in &kernel {
+                let (source_x, source_y) = (x + dx, y + dy);
+                if source_x < 0
+                    || source_y < 0
+                    || source_x >= width as i32
+                    || source_y >= height as i32
                 {
-                    let source_pixel = source.get_pixel(x as u32, y as u32);
-                    if source_pixel[3] > 0 {
-                        // 只复制非透明像素
-                        target.put_pixel(target_x as u32, target_y as u32, *source_pixel);
+                    continue;
+                }
+                value = value.max(coverage[source_y as usize * width + source_x as usize] * weight);
+            }
+            output[y as usize * width + x as usize] = value;
+        }
+    }
+    output
+}
+
+/// 高斯模糊覆盖率图，radius 为模糊半径（sigma 取其一半），分水平和垂直两次进行
+fn blur_coverage(coverage: &[f32], width: usize, height: usize, radius: f32) -> Vec<f32> {
+    if radius <= 0.0 {
+        return coverage.to_vec();
+    }
+    let sigma = (radius / 2.0).max(0.5);
+    let reach = radius.ceil() as i32;
+    let weights: Vec<f32> = (-reach..=reach)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+
+    let pass = |input: &[f32], horizontal: bool| -> Vec<f32> {
+        let mut output = vec![0.0_f32; input.len()];
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let mut value = 0.0;
+                for (index, weight) in weights.iter().enumerate() {
+                    let offset = index as i32 - reach;
+                    let (source_x, source_y) = if horizontal {
+                        (x + offset, y)
+                    } else {
+                        (x, y + offset)
+                    };
+                    if source_x < 0
+                        || source_y < 0
+                        || source_x >= width as i32
+                        || source_y >= height as i32
+                    {
+                        continue;
                     }
+                    value += input[source_y as usize * width + source_x as usize] * weight;
                 }
+                output[y as usize * width + x as usize] = value / total;
             }
         }
+        output
+    };
+
+    pass(&pass(coverage, true), false)
+}
+
+/// 在覆盖率图中双线性插值取 (x, y) 处的值，像素中心位于 +0.5 处，超出范围的部分视为 0
+fn sample_coverage(coverage: &[f32], width: usize, height: usize, x: f32, y: f32) -> f32 {
+    let fx = x - 0.5;
+    let fy = y - 0.5;
+    let x0 = fx.floor();
+    let y0 = fy.floor();
+    let tx = fx - x0;
+    let ty = fy - y0;
+
+    let value_at = |x: f32, y: f32| {
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            0.0
+        } else {
+            coverage[y as usize * width + x as usize]
+        }
+    };
+
+    let top = value_at(x0, y0) * (1.0 - tx) + value_at(x0 + 1.0, y0) * tx;
+    let bottom = value_at(x0, y0 + 1.0) * (1.0 - tx) + value_at(x0 + 1.0, y0 + 1.0) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+/// 直通透明度的 source-over 混合，源像素先乘以染色
+pub fn blend_over(target: &mut Rgba<u8>, source: &Rgba<u8>, tint: &[f32; 4]) {
+    let source_alpha = source[3] as f32 / 255.0 * tint[3];
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let target_alpha = target[3] as f32 / 255.0;
+    let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+
+    for channel in 0..3 {
+        let source_value = source[channel] as f32 / 255.0 * tint[channel];
+        let target_value = target[channel] as f32 / 255.0;
+        let value = (source_value * source_alpha
+            + target_value * target_alpha * (1.0 - source_alpha))
+            / alpha;
+        target[channel] = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    target[3] = (alpha * 255.0).round() as u8;
+}
+
+/// 排好的字形像素的包围盒；没有可见字形时返回 None
+fn glyph_bounds(glyphs: &[PositionedGlyph]) -> Option<rusttype::Rect<i32>> {
+    glyphs
+        .iter()
+        .filter_map(|glyph| glyph.pixel_bounding_box())
+        .reduce(|bounds, glyph_bounds| rusttype::Rect {
+            min: rusttype::point(
+                bounds.min.x.min(glyph_bounds.min.x),
+                bounds.min.y.min(glyph_bounds.min.y),
+            ),
+            max: rusttype::point(
+                bounds.max.x.max(glyph_bounds.max.x),
+                bounds.max.y.max(glyph_bounds.max.y),
+            ),
+        })
+}
+
+/// 在空白处贪心换行，使每行的墨迹宽度不超过 max_width；整行放得下时保持原样，
+/// 单个词比 max_width 还宽时单独占一行，由字号二分查找缩小
+fn wrap_line(line: &str, fonts: &FontStack, scale: Scale, max_width: f32) -> Vec<String> {
+    let line_width = |text: &str| {
+        glyph_bounds(&fonts.layout(text, scale)).map_or(0.0, |bounds| bounds.width() as f32)
+    };
+    if line_width(line) <= max_width {
+        return vec![line.to_string()];
+    }
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        if current.is_empty() {
+            current = word.to_string();
+            continue;
+        }
+        let candidate = format!("{} {}", current, word);
+        if line_width(&candidate) <= max_width {
+            current = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut current, word.to_string()));
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// 大写字母 H 顶端到基线的距离；字体没有 H 时退回 ascent
+fn cap_height(font: &Font, scale: Scale) -> f32 {
+    font.glyph('H')
+        .scaled(scale)
+        .exact_bounding_box()
+        .map(|bounds| -bounds.min.y)
+        .filter(|height| *height > 0.0)
+        .unwrap_or_else(|| font.v_metrics(scale).ascent)
+}
+
+/// 第 index 个变体在 RGB 三个通道上的颜色偏移
+pub fn variation_shifts(index: u32) -> [f32; 3] {
+    // 根据图片索引自动生成色调，均匀分布在360度色环上
+    // 使用黄金比例来获得更好的颜色分布
+    let golden_ratio = 1.618033988749895;
+    let current_hue = (index as f32 * 360.0 * golden_ratio) % 360.0;
+
+    // 将色调转换为弧度
+    let hue_rad = current_hue.to_radians();
+
+    // 应用色调变化到RGB通道
+    // 使用不同的相位偏移来创建更丰富的颜色变化
+    let r_phase = hue_rad;
+    let g_phase = hue_rad + 2.0943951023931953; // +120度
+    let b_phase = hue_rad + 4.1887902047863905; // +240度
+
+    [
+        0.3 * r_phase.cos(),
+        0.3 * g_phase.cos(),
+        0.3 * b_phase.cos(),
+    ]
+}
+
+/// 第 index 个变体的插槽颜色：与贴图变色相同的色调，叠加在白色上，作为乘法染色使用
+pub fn variation_tint(index: u32) -> [u8; 3] {
+    variation_shifts(index).map(|shift| ((0.7 + shift).clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+// 反预乘和预乘都按四舍五入计算，未被改动的像素经过一次往返后保持不变
+fn unpremultiply(rgba_img: &mut RgbaImage) {
+    for pixel in rgba_img.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in 0..3 {
+            let value = (pixel[channel] as u32 * 255 + alpha / 2) / alpha;
+            pixel[channel] = value.min(255) as u8;
+        }
+    }
+}
+
+fn premultiply(rgba_img: &mut RgbaImage) {
+    for pixel in rgba_img.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        if alpha == 255 {
+            continue;
+        }
+        for channel in 0..3 {
+            pixel[channel] = ((pixel[channel] as u32 * alpha + 127) / 255) as u8;
+        }
     }
 }
@@ -0,0 +1,90 @@
+use crate::atlas::Atlas;
+use crate::skeleton::{self, SkeletonFile, SkeletonHeader};
+use anyhow::Result;
+use std::path::Path;
+
+#[derive(clap::Args)]
+pub struct InspectArgs {
+    /// Spine文件路径（不包含扩展名）
+    #[arg(help = "Spine文件路径，例如: ./data/lixiaolong")]
+    pub asset: String,
+}
+
+/// 打印图集和骨骼文件的概要信息，并检查两者的版本是否一致
+pub fn run(args: &InspectArgs) -> Result<()> {
+    let atlas_path = format!("{}.atlas", args.asset);
+    let atlas = if Path::new(&atlas_path).exists() {
+        Some(Atlas::load(&atlas_path)?)
+    } else {
+        None
+    };
+
+    match &atlas {
+        Some(atlas) => {
+            println!("Atlas: {} ({})", atlas_path, atlas.format.as_str());
+            for page in &atlas.pages {
+                println!(
+                    "  page '{}': {}x{}, pma: {}, {} regions",
+                    page.name,
+                    page.width,
+                    page.height,
+                    page.pma,
+                    page.regions.len()
+                );
+            }
+        }
+        None => println!("Atlas: not found"),
+    }
+
+    let skeletons = SkeletonFile::find_all(&args.asset);
+    if skeletons.is_empty() {
+        println!("Skeleton: not found");
+    }
+
+    for skeleton_file in &skeletons {
+        println!("Skeleton: {}", skeleton_file.path);
+        let header = match skeleton_file.read_header() {
+            Ok(header) => header,
+            Err(error) => {
+                println!("  Error: {:#}", error);
+                continue;
+            }
+        };
+        print_header(&header);
+
+        if let Some(warning) = atlas
+            .as_ref()
+            .and_then(|atlas| skeleton::version_mismatch(&header, atlas))
+        {
+            println!("Warning: {}", warning);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_header(header: &SkeletonHeader) {
+    println!("  hash: {}", header.hash);
+    println!("  spine version: {}", header.version);
+    println!(
+        "  bounds: x={}, y={}, width={}, height={}",
+        header.x, header.y, header.width, header.height
+    );
+    if let Some(reference_scale) = header.reference_scale {
+        println!("  reference scale: {}", reference_scale);
+    }
+    match &header.fps {
+        Some(fps) => {
+            println!("  fps: {}", fps);
+            println!(
+                "  images path: {}",
+                header.images_path.as_deref().unwrap_or("")
+            );
+            println!(
+                "  audio path: {}",
+                header.audio_path.as_deref().unwrap_or("")
+            );
+        }
+        None => println!("  nonessential data: not exported"),
+    }
+}
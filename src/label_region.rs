@@ -0,0 +1,241 @@
+use crate::atlas::{Atlas, AtlasPage, AtlasRegion};
+use crate::geometry::PixelRect;
+use crate::merge::PACK_PADDING;
+use anyhow::{Context, Result};
+use image::{imageops, DynamicImage, RgbaImage};
+use std::fs;
+use std::path::Path;
+
+/// 标签区域在图集中的位置
+enum LabelPlacement {
+    /// 放在原纸张的空白处
+    FreeSpace { x: u32, y: u32 },
+    /// 原纸张放不下，放到新增的纸张上
+    NewPage,
+}
+
+/// 把文字作为独立区域加入图集：原纸张的美术像素保持不变
+///
+/// 每个变体的标签大小相同，位置只计算一次；优先放在原纸张上不与任何区域重叠、
+/// 且完全透明的空白处，放不下时新增一张只包含标签的纸张 `<图片名>_label.png`。
+pub struct LabelLayout {
+    png_path: String,
+    page_name: String,
+    page_image: RgbaImage,
+    width: u32,
+    height: u32,
+    placement: LabelPlacement,
+}
+
+impl LabelLayout {
+    pub fn new(atlas: &Atlas, png_path: &str, width: u32, height: u32) -> Result<Self> {
+        let page = atlas
+            .page_for_image(png_path)
+            .ok_or_else(|| anyhow::anyhow!("No atlas page matches {}", png_path))?;
+        let page_image = image::open(png_path)
+            .with_context(|| format!("Failed to open image: {}", png_path))?
+            .to_rgba8();
+
+        let placement = match find_free_space(page, &page_image, width, height) {
+            Some((x, y)) => {
+                println!(
+                    "Label region ({}x{}) fits into free space of page '{}' at {}, {}",
+                    width, height, page.name, x, y
+                );
+                LabelPlacement::FreeSpace { x, y }
+            }
+            None => {
+                println!(
+                    "Label region ({}x{}) does not fit into page '{}', adding a new page",
+                    width, height, page.name
+                );
+                LabelPlacement::NewPage
+            }
+        };
+
+        Ok(Self {
+            png_path: png_path.to_string(),
+            page_name: page.name.clone(),
+            page_image,
+            width,
+            height,
+            placement,
+        })
+    }
+
+    /// 返回登记了标签区域的图集；output_png_path 是变体的纸张图片，新增纸张以它命名
+    pub fn variant_atlas(
+        &self,
+        atlas: &Atlas,
+        region_name: &str,
+        output_png_path: &str,
+    ) -> Result<Atlas> {
+        if atlas
+            .pages
+            .iter()
+            .any(|page| page.region(region_name).is_some())
+        {
+            anyhow::bail!("Atlas already has a region named '{}'", region_name);
+        }
+
+        let mut atlas = atlas.clone();
+        let mut region = AtlasRegion::new(region_name);
+        region.width = self.width;
+        region.height = self.height;
+        region.original_width = self.width;
+        region.original_height = self.height;
+
+        let page_name = match self.placement {
+            LabelPlacement::FreeSpace { x, y } => {
+                region.x = x as i32;
+                region.y = y as i32;
+                self.page_name.clone()
+            }
+            LabelPlacement::NewPage => {
+                region.x = PACK_PADDING as i32;
+                region.y = PACK_PADDING as i32;
+                let (page_width, page_height) = self.new_page_size();
+                let page_name = file_name(&label_page_path(output_png_path));
+                atlas.add_page(&page_name, page_width, page_height)?;
+                page_name
+            }
+        };
+
+        atlas.add_region(&page_name, &region)?;
+        Ok(atlas)
+    }
+
+    /// 写出变体的纸张图片（以及新增的标签纸张）
+    pub fn write(&self, label: &RgbaImage, output_png_path: &str) -> Result<()> {
+        match self.placement {
+            LabelPlacement::FreeSpace { x, y } => {
+                let mut page_image = self.page_image.clone();
+                imageops::replace(&mut page_image, label, x as i64, y as i64);
+                DynamicImage::ImageRgba8(page_image).save(output_png_path)?;
+            }
+            LabelPlacement::NewPage => {
+                // 原纸张原样复制
+                fs::copy(&self.png_path, output_png_path)
+                    .with_context(|| format!("Failed to copy image: {}", self.png_path))?;
+
+                let (page_width, page_height) = self.new_page_size();
+                let mut page_image = RgbaImage::new(page_width, page_height);
+                imageops::replace(
+                    &mut page_image,
+                    label,
+                    PACK_PADDING as i64,
+                    PACK_PADDING as i64,
+                );
+                DynamicImage::ImageRgba8(page_image).save(label_page_path(output_png_path))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 新增的标签纸张的图片路径；标签放在原纸张上时返回 None
+    pub fn label_page_path(&self, output_png_path: &str) -> Option<String> {
+        match self.placement {
+            LabelPlacement::FreeSpace { .. } => None,
+            LabelPlacement::NewPage => Some(label_page_path(output_png_path)),
+        }
+    }
+
+    fn new_page_size(&self) -> (u32, u32) {
+        (
+            self.width + PACK_PADDING * 2,
+            self.height + PACK_PADDING * 2,
+        )
+    }
+}
+
+/// 按从上到下、从左到右的顺序，在已有区域的右侧和下方寻找能放下标签的位置
+fn find_free_space(
+    page: &AtlasPage,
+    page_image: &RgbaImage,
+    width: u32,
+    height: u32,
+) -> Option<(u32, u32)> {
+    let occupied: Vec<PixelRect> = page
+        .regions
+        .iter()
+        .map(|region| region.packed_bounds())
+        .collect();
+
+    let padding = PACK_PADDING as i32;
+    let mut xs = vec![padding];
+    let mut ys = vec![padding];
+    for bounds in &occupied {
+        xs.push(bounds.right() + padding);
+        ys.push(bounds.bottom() + padding);
+    }
+    xs.sort_unstable();
+    xs.dedup();
+    ys.sort_unstable();
+    ys.dedup();
+
+    let page_bounds = PixelRect::new(0, 0, page.width, page.height)
+        .intersect(&PixelRect::new(
+            0,
+            0,
+            page_image.width(),
+            page_image.height(),
+        ))?
+        .inset(PACK_PADDING);
+
+    for &y in &ys {
+        for &x in &xs {
+            let candidate = PixelRect::new(x, y, width, height);
+            if !page_bounds.contains_rect(&candidate) {
+                continue;
+            }
+
+            // 四周留出内边距，避免线性过滤时与相邻区域相互渗色
+            let padded = PixelRect::new(
+                x - padding,
+                y - padding,
+                width + PACK_PADDING * 2,
+                height + PACK_PADDING * 2,
+            );
+            if occupied
+                .iter()
+                .any(|bounds| bounds.intersect(&padded).is_some())
+            {
+                continue;
+            }
+            if is_transparent(page_image, &padded) {
+                return Some((x as u32, y as u32));
+            }
+        }
+    }
+
+    None
+}
+
+/// 范围内（超出图片的部分忽略）的像素是否全部透明
+fn is_transparent(image: &RgbaImage, rect: &PixelRect) -> bool {
+    let image_bounds = PixelRect::new(0, 0, image.width(), image.height());
+    let Some(rect) = rect.intersect(&image_bounds) else {
+        return true;
+    };
+    (rect.y..rect.bottom())
+        .all(|y| (rect.x..rect.right()).all(|x| image.get_pixel(x as u32, y as u32)[3] == 0))
+}
+
+/// 新增纸张的图片路径：`hero.png` 对应 `hero_label.png`
+fn label_page_path(output_png_path: &str) -> String {
+    let path = Path::new(output_png_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_label.png", stem))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
@@ -1,39 +1,396 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
 
+mod atlas;
 mod file_manager;
+mod font;
+mod geometry;
 mod gui;
 mod image_processor;
+mod inspect;
+mod label_region;
+mod manifest;
+mod merge;
+mod preview;
+mod skeleton;
+mod validate;
 
+use atlas::Atlas;
 use file_manager::FileManager;
-use gui::RectSelector;
-use image_processor::ImageProcessor;
+use font::{FontChoice, FontStack, LoadedFont};
+use geometry::{PixelMask, PixelRect};
+use gui::{RectSelector, TextDirection};
+use image_processor::{
+    DrawReport, HorizontalAlign, ImageProcessor, Outline, OutlineJoin, PmaMode, Shadow, TextAnchor,
+    TextEffects, TextLayout, TextPadding, VariationMode, VerticalAlign,
+};
+use label_region::LabelLayout;
+use manifest::Manifest;
+use skeleton::{SkeletonFile, SkeletonFormat};
 
 #[derive(Parser)]
 #[command(name = "assext")]
 #[command(about = "Asset文件扩展工具 - 在指定区域生成数字")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 校验图集与PNG纸张是否一致
+    Validate(validate::ValidateArgs),
+    /// 查看图集和骨骼文件的信息
+    Inspect(inspect::InspectArgs),
+}
+
+/// 不带子命令时的生成参数
+#[derive(clap::Args)]
+#[command(group(
+    clap::ArgGroup::new("region_source")
+        .args(["original_coords", "slot", "attachment"])
+        .multiple(true)
+))]
 struct Args {
     /// Spine文件路径（不包含扩展名）
-    #[arg(help = "Spine文件路径，例如: ./data/lixiaolong")]
-    spine_path: String,
+    #[arg(required = true, help = "Spine文件路径，例如: ./data/lixiaolong")]
+    spine_path: Option<String>,
 
     /// 输出目录
-    #[arg(help = "输出目录，例如: output")]
-    output_dir: String,
+    #[arg(required = true, help = "输出目录，例如: output")]
+    output_dir: Option<String>,
 
     /// 生成数量
-    #[arg(help = "生成的文件数量，例如: 3")]
-    count: u32,
+    #[arg(required = true, help = "生成的文件数量，例如: 3")]
+    count: Option<u32>,
+
+    /// 图集区域内边距
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "有图集时，文字会被裁剪到所在区域向内收缩该像素数后的范围内"
+    )]
+    region_padding: u32,
+
+    /// 预乘透明度模式
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = PmaMode::Auto,
+        help = "图片是否为预乘透明度；auto 时读取图集的 pma 字段或根据像素判断"
+    )]
+    pma: PmaMode,
+
+    /// 直接指定文字矩形
+    #[arg(long, help = "直接指定文字矩形 x,y,width,height，指定后不再打开GUI")]
+    rect: Option<PixelRect>,
+
+    /// 文字矩形所在的图集区域
+    #[arg(
+        long,
+        requires = "region_source",
+        help = "文字矩形所在的图集区域名，配合 --original-coords、--slot 或 --attachment 使用"
+    )]
+    region: Option<String>,
+
+    /// 通过骨骼插槽确定文字所在的区域
+    #[arg(
+        long,
+        conflicts_with = "attachment",
+        help = "文字放在该插槽初始姿势附件所用的图集区域上；同时给出 --rect 时按区域的原始坐标解释"
+    )]
+    slot: Option<String>,
+
+    /// 通过附件名确定文字所在的区域
+    #[arg(
+        long,
+        help = "文字放在该附件所用的图集区域上；同时给出 --rect 时按区域的原始坐标解释"
+    )]
+    attachment: Option<String>,
+
+    /// 文字矩形使用区域的原始坐标
+    #[arg(
+        long,
+        requires_all = ["rect", "region"],
+        help = "--rect 是相对于区域原始（未去除空白）图片左上角的坐标，会经过偏移和旋转映射到纸张像素"
+    )]
+    original_coords: bool,
+
+    /// 文字朝向（仅在使用 --rect 时生效）
+    #[arg(long, value_enum, default_value_t = TextDirection::Right)]
+    direction: TextDirection,
+
+    /// 文字旋转角度（仅在使用 --rect 时生效）
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        help = "文字顺时针旋转的角度（度），0 为正常水平方向；指定后代替 --direction"
+    )]
+    angle: Option<f32>,
+
+    /// 镜像文字（仅在使用 --rect 时生效）
+    #[arg(long, help = "文字左右镜像后再旋转")]
+    mirror: bool,
+
+    /// 文字颜色（仅在使用 --rect 时生效）
+    #[arg(long, value_parser = parse_color, default_value = "#000000")]
+    text_color: egui::Color32,
+
+    /// 标签文字
+    #[arg(
+        long,
+        default_value = "{n}",
+        help = "标签文字模板：{n} 替换为变体编号，\\n 表示换行，例如 \"Alice\\n{n}\""
+    )]
+    label_text: String,
+
+    /// 文字与矩形边缘的距离
+    #[arg(
+        long,
+        default_value = "0",
+        value_parser = TextPadding::parse,
+        help = "文字的实际字形与矩形各边之间至少保留的像素，字号按扣除后的范围取最大值；一个值用于四边，两个值为 上下,左右，四个值为 上,右,下,左"
+    )]
+    text_padding: TextPadding,
+
+    /// 行距
+    #[arg(long, default_value_t = 1.0, help = "多行文字的行距，字体行高的倍数")]
+    line_spacing: f32,
+
+    /// 书写方向上的对齐
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = HorizontalAlign::Center,
+        help = "各行之间以及文字在矩形中沿书写方向的对齐方式"
+    )]
+    align: HorizontalAlign,
+
+    /// 行堆叠方向上的对齐
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VerticalAlign::Middle,
+        help = "文字在矩形中垂直于书写方向的对齐方式"
+    )]
+    vertical_align: VerticalAlign,
+
+    /// 自动换行
+    #[arg(
+        long,
+        help = "一行放不下时在空白处自动换行；默认只在标签文字中的换行处分行"
+    )]
+    wrap: bool,
+
+    /// 字体文件
+    #[arg(
+        long,
+        help = "绘制文字所用的字体文件（.ttf / .otf / .ttc），不指定时使用系统字体"
+    )]
+    font: Option<String>,
+
+    /// 字体集合中的字体序号
+    #[arg(
+        long,
+        default_value_t = 0,
+        requires = "font",
+        help = ".ttc / .otc 字体集合中使用第几个字体（从 0 开始）"
+    )]
+    font_index: u32,
+
+    /// 后备字体
+    #[arg(
+        long,
+        value_name = "PATH[:INDEX]",
+        value_parser = FontChoice::parse,
+        help = "主字体缺字时按顺序逐字使用的后备字体，可多次指定；字体集合用 :INDEX 选择字体（之后还会尝试系统中文字体）"
+    )]
+    fallback_font: Vec<FontChoice>,
+
+    /// 必须显式指定字体
+    #[arg(
+        long,
+        help = "必须通过 --font（或 GUI）指定字体，不回退到系统字体或内置字体，避免换一台机器后输出悄悄改变"
+    )]
+    require_font: bool,
+
+    /// 文字在垂直于书写方向上的对齐方式
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TextAnchor::Ink,
+        help = "ink 按字形实际像素对齐；baseline 按首行 ascent 到末行 descent 对齐；cap-height 按首行大写字母顶端到末行基线对齐，多个标签需要对齐基线时使用"
+    )]
+    text_anchor: TextAnchor,
+
+    /// 文字描边（仅在使用 --rect 时生效）
+    #[arg(
+        long,
+        help = "给文字加描边，样式由 --outline-width / --outline-color / --outline-join 设置"
+    )]
+    outline: bool,
+
+    /// 描边宽度
+    #[arg(
+        long,
+        default_value_t = 2.0,
+        requires = "outline",
+        help = "描边宽度（像素）"
+    )]
+    outline_width: f32,
+
+    /// 描边颜色
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff", requires = "outline")]
+    outline_color: egui::Color32,
+
+    /// 描边拐角形状
+    #[arg(long, value_enum, default_value_t = OutlineJoin::Round, requires = "outline")]
+    outline_join: OutlineJoin,
+
+    /// 文字投影（仅在使用 --rect 时生效）
+    #[arg(
+        long,
+        help = "给文字加投影，样式由 --shadow-offset / --shadow-blur / --shadow-color / --shadow-opacity 设置"
+    )]
+    shadow: bool,
+
+    /// 投影偏移
+    #[arg(
+        long,
+        value_parser = parse_offset,
+        default_value = "2,2",
+        allow_hyphen_values = true,
+        requires = "shadow",
+        help = "投影在纹理上的偏移 x,y（像素），不随文字旋转"
+    )]
+    shadow_offset: (f32, f32),
+
+    /// 投影模糊半径
+    #[arg(
+        long,
+        default_value_t = 2.0,
+        requires = "shadow",
+        help = "投影的模糊半径（像素），0 为硬边"
+    )]
+    shadow_blur: f32,
+
+    /// 投影颜色
+    #[arg(long, value_parser = parse_color, default_value = "#000000", requires = "shadow")]
+    shadow_color: egui::Color32,
+
+    /// 投影不透明度
+    #[arg(
+        long,
+        default_value_t = 0.6,
+        requires = "shadow",
+        help = "投影的不透明度，0 到 1"
+    )]
+    shadow_opacity: f32,
+
+    /// 启用颜色变化（仅在使用 --rect 时生效）
+    #[arg(long)]
+    color_variation: bool,
+
+    /// 把文字裁剪到网格可见的三角形内
+    #[arg(
+        long,
+        help = "文字所在区域被 .json 骨骼中的网格使用时，丢弃落在网格三角形之外（游戏中不可见）的文字像素"
+    )]
+    clip_to_mesh: bool,
+
+    /// 颜色变化的实现方式
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VariationMode::Texture,
+        help = "texture 直接修改贴图像素；slot 把颜色写入 .json 骨骼的插槽颜色，贴图只绘制文字"
+    )]
+    variation_mode: VariationMode,
+
+    /// 需要染色的插槽
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "--variation-mode slot 时只给这些插槽染色（逗号分隔），默认所有插槽"
+    )]
+    tint_slot: Vec<String>,
+
+    /// 同时写入双色染色的暗色
+    #[arg(
+        long,
+        help = "--variation-mode slot 时同时写入插槽的 dark 颜色（双色染色），取染色的四分之一亮度"
+    )]
+    dark_tint: bool,
+
+    /// 为每个变体生成不同的骨骼哈希
+    #[arg(
+        long,
+        help = "根据每个变体的内容重写 .skel / .json 中的骨骼哈希，避免运行时按哈希复用缓存"
+    )]
+    unique_hash: bool,
+
+    /// 把所有变体合并为一个骨骼的多个皮肤
+    #[arg(
+        long,
+        conflicts_with = "unique_hash",
+        help = "只输出一套骨骼和图集：每个变体是一个皮肤，文字所在区域打包到新增的图集纸张上（需要 .atlas 和 .json 骨骼）"
+    )]
+    merge_skins: bool,
+
+    /// 把文字作为图集中的新区域输出
+    #[arg(
+        long,
+        conflicts_with = "merge_skins",
+        help = "文字单独绘制成一张小图，放到纸张的空白处（放不下时新增纸张），并在图集中登记为新区域，原有像素保持不变（需要 .atlas）"
+    )]
+    label_region: bool,
+
+    /// 新区域的名称前缀
+    #[arg(
+        long,
+        default_value = "number",
+        requires = "label_region",
+        help = "--label-region 时新区域命名为 <前缀>_NN"
+    )]
+    label_region_name: String,
+
+    /// 子目录中的文件按变体命名
+    #[arg(
+        long,
+        conflicts_with = "merge_skins",
+        help = "子目录中的 .atlas / .png / .skel / .json 改名为 <名称>_NN.*，并同步修改图集的纸张名和骨骼的图片目录"
+    )]
+    rename_variants: bool,
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Validate(validate_args)) => validate::run(&validate_args),
+        Some(Command::Inspect(inspect_args)) => inspect::run(&inspect_args),
+        None => generate(&cli.args),
+    }
+}
+
+fn generate(args: &Args) -> Result<()> {
+    // 没有子命令时 clap 已保证这三个参数都存在
+    let (Some(spine_path), Some(output_dir), Some(count)) =
+        (&args.spine_path, &args.output_dir, args.count)
+    else {
+        anyhow::bail!("SPINE_PATH, OUTPUT_DIR and COUNT are required");
+    };
 
     // 检查文件是否存在
-    let atlas_path = format!("{}.atlas", args.spine_path);
-    let png_path = format!("{}.png", args.spine_path);
-    let skel_path = format!("{}.skel", args.spine_path);
+    let atlas_path = format!("{}.atlas", spine_path);
+    let png_path = format!("{}.png", spine_path);
 
     // PNG file is required
     if !Path::new(&png_path).exists() {
@@ -42,35 +399,168 @@ fn main() -> Result<()> {
 
     // 检查其他文件是否存在
     let has_atlas = Path::new(&atlas_path).exists();
-    let has_skel = Path::new(&skel_path).exists();
+    let skeletons = SkeletonFile::find_all(spine_path);
+    let has_skel = !skeletons.is_empty();
 
     // 获取文件名（不包含路径）
-    let spine_name = Path::new(&args.spine_path)
+    let spine_name = Path::new(spine_path)
         .file_stem()
         .unwrap()
         .to_string_lossy()
         .to_string();
 
-    // 打开GUI选择矩形区域
+    let atlas = if has_atlas {
+        let atlas = Atlas::load(&atlas_path)?;
+        println!("Atlas format: {}", atlas.format.as_str());
+        Some(atlas)
+    } else {
+        None
+    };
+
+    // 骨骼与图集版本不一致时提前提醒，避免导出的资源在运行时才出错
+    if let Some(atlas) = &atlas {
+        for skeleton_file in &skeletons {
+            let header = match skeleton_file.read_header() {
+                Ok(header) => header,
+                Err(error) => {
+                    println!(
+                        "Warning: cannot read the version of {}, skipping the version check: {:#}",
+                        skeleton_file.path, error
+                    );
+                    continue;
+                }
+            };
+            if let Some(warning) = skeleton::version_mismatch(&header, atlas) {
+                println!("Warning: {}", warning);
+            }
+        }
+    }
+
+    // 按插槽或附件查找时，文字放在对应的区域上
+    let target_region = if args.slot.is_some() || args.attachment.is_some() {
+        Some(skeleton_target_region(args, &skeletons)?)
+    } else if args.original_coords {
+        args.region.clone()
+    } else {
+        None
+    };
 
-    // 临时测试：使用固定矩形区域
-    let rect = RectSelector::select_rect(&png_path)?;
+    // 先加载字体，字体有误时不必打开GUI；--require-font 时不使用系统中的后备字体，保证输出只取决于指定的字体
+    let mut fonts = FontStack::load(
+        font_choice(args).as_ref(),
+        &args.fallback_font,
+        !args.require_font,
+    )?;
+
+    // 命令行指定了矩形或区域时直接使用，否则打开GUI选择矩形区域
+    let rect = if args.rect.is_some() || target_region.is_some() {
+        rect_from_args(
+            args,
+            args.rect,
+            target_region.as_deref(),
+            atlas.as_ref(),
+            &png_path,
+        )?
+    } else {
+        let preview =
+            setup_pose_preview(args, &skeletons, atlas.as_ref(), &png_path, count, &fonts);
+        RectSelector::select_rect(&png_path, preview, font_choice(args))?
+    };
 
     println!(
         "Selected rectangle region: x={}, y={}, width={}, height={}",
         rect.x, rect.y, rect.width, rect.height
     );
 
-    // 创建输出目录
-    let file_manager = FileManager::new(&args.output_dir, &spine_name, has_atlas || has_skel);
-    file_manager.create_output_dirs(args.count)?;
-
     // 处理每个文件
-    let image_processor = ImageProcessor::new(&png_path);
+    let mut image_processor = ImageProcessor::new(&png_path);
+    image_processor.set_text_layout(text_layout(args));
+
+    // GUI 中可能换了字体，以选择结果为准
+    if args.require_font && rect.font.is_none() {
+        anyhow::bail!("--require-font is set but no font was chosen, pass one with --font");
+    }
+    if rect.font != font_choice(args) {
+        fonts.set_primary(LoadedFont::load_or_system(rect.font.as_ref())?);
+    }
+    println!("Font: {}", fonts.describe());
+    let mut manifest = Manifest::new(spine_path, &fonts);
+    image_processor.set_fonts(fonts);
+
+    // 有图集时，把绘制限制在文字矩形所在的区域内，避免渗入相邻区域
+    if let Some(atlas) = &atlas {
+        LabelClip::new(args, atlas, &png_path, &skeletons).apply(
+            &mut image_processor,
+            &rect,
+            true,
+        )?;
+    }
+
+    let premultiplied = is_premultiplied(args.pma, atlas.as_ref(), &png_path, &image_processor);
+    if premultiplied {
+        println!("Treating {} as premultiplied alpha", png_path);
+    }
+    image_processor.set_premultiplied(premultiplied);
+
+    // 插槽染色只能写入 JSON 骨骼
+    let slot_tint = rect.enable_color_variation && args.variation_mode == VariationMode::Slot;
+    if slot_tint {
+        if args.merge_skins {
+            anyhow::bail!("--variation-mode slot cannot be combined with --merge-skins, skins share the setup pose colors");
+        }
+        if !skeletons
+            .iter()
+            .any(|skeleton_file| skeleton_file.format == SkeletonFormat::Json)
+        {
+            anyhow::bail!("--variation-mode slot requires a .json skeleton");
+        }
+        if skeletons
+            .iter()
+            .any(|skeleton_file| skeleton_file.format == SkeletonFormat::Binary)
+        {
+            println!("Warning: binary skeletons keep their original slot colors, only the .json skeleton is tinted");
+        }
+    }
+
+    if args.merge_skins {
+        let atlas = atlas
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("--merge-skins requires an .atlas file"))?;
+        let merge_input = merge::MergeInput {
+            spine_path,
+            spine_name: &spine_name,
+            png_path: &png_path,
+            output_dir,
+            count,
+            atlas,
+            skeletons: &skeletons,
+            rect: &rect,
+            label_template: &args.label_text,
+        };
+        merge::run(&merge_input, &image_processor, &mut manifest)?;
+        let manifest_path = manifest.save(output_dir)?;
+        println!("Manifest written to {}", manifest_path);
+        return Ok(());
+    }
+
+    // 标签作为独立区域时，位置对所有变体都相同
+    let label_layout = if args.label_region {
+        let atlas = atlas
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("--label-region requires an .atlas file"))?;
+        Some(LabelLayout::new(atlas, &png_path, rect.width, rect.height)?)
+    } else {
+        None
+    };
 
-    for i in 1..=args.count {
+    // 创建输出目录
+    let mut file_manager = FileManager::new(output_dir, &spine_name, has_atlas || has_skel);
+    file_manager.set_rename_variants(args.rename_variants);
+    file_manager.create_output_dirs(count)?;
+
+    for i in 1..=count {
         // 根据数量决定数字格式：超过99个使用3位数字，否则使用2位数字
-        let (dir_name, number_text) = if args.count > 99 {
+        let (dir_name, number_text) = if count > 99 {
             // 3位数字格式：001, 002, 003...
             let formatted_num = format!("{:03}", i);
             (format!("{}_{}", spine_name, formatted_num), formatted_num)
@@ -80,38 +570,600 @@ fn main() -> Result<()> {
             (format!("{}_{}", spine_name, formatted_num), formatted_num)
         };
 
-        // 复制文件（如果存在同名文件）
-        file_manager.copy_files(&dir_name, &atlas_path, &skel_path, has_atlas, has_skel)?;
-
-        // 在PNG上绘制数字
         let output_png_path = if has_atlas || has_skel {
             // 如果有其他文件：在子目录中生成图片
-            format!("{}/{}/{}.png", args.output_dir, dir_name, spine_name)
+            file_manager.png_target(&dir_name)
         } else {
             // 如果只有PNG文件：直接在output目录下生成带编号的图片
-            format!("{}/{}_{}.png", args.output_dir, spine_name, number_text)
+            format!("{}/{}_{}.png", output_dir, spine_name, number_text)
         };
 
-        image_processor.draw_text_in_rect_with_color_variation(
-            &output_png_path,
-            &number_text,
-            &rect,
-            rect.enable_color_variation,
-            0.0, // base_hue 不再使用，传递0.0
-            i,
-        )?;
+        // 复制文件（如果存在同名文件）
+        let variant_atlas = match (&label_layout, &atlas) {
+            (Some(label_layout), Some(atlas)) => Some(label_layout.variant_atlas(
+                atlas,
+                &format!("{}_{}", args.label_region_name, number_text),
+                &output_png_path,
+            )?),
+            _ => atlas.clone(),
+        };
+        file_manager.copy_files(&dir_name, variant_atlas.as_ref(), &skeletons)?;
+
+        // 在PNG上绘制标签文字
+        let text = merge::label_text(&args.label_text, &number_text);
+        let report = if let Some(label_layout) = &label_layout {
+            let label = image_processor.render_label(
+                &text,
+                &rect,
+                rect.enable_color_variation && !slot_tint,
+                i,
+            )?;
+            label_layout.write(&label, &output_png_path)?;
+            DrawReport::default()
+        } else {
+            image_processor.draw_text_in_rect_with_color_variation(
+                &output_png_path,
+                &text,
+                &rect,
+                rect.enable_color_variation && !slot_tint,
+                0.0, // base_hue 不再使用，传递0.0
+                i,
+            )?
+        };
+
+        if report.clipped_pixels > 0 {
+            println!(
+                "Warning: {} text pixels fell outside the clip area and were discarded: {}",
+                report.clipped_pixels, output_png_path
+            );
+        }
+
+        if slot_tint {
+            let tint = image_processor::variation_tint(i);
+            let dark = args.dark_tint.then(|| tint.map(|channel| channel / 4));
+            let target = file_manager.skeleton_target(&dir_name, SkeletonFormat::Json);
+            skeleton::tint_slots(&target, &args.tint_slot, tint, dark)?;
+            println!(
+                "Slot tint of {}: {:02x}{:02x}{:02x}",
+                target, tint[0], tint[1], tint[2]
+            );
+        }
+
+        if report.hidden_pixels > 0 {
+            println!(
+                "Warning: {} text pixels lie outside the visible mesh triangles and will not show in game: {}",
+                report.hidden_pixels, output_png_path
+            );
+        }
+
+        let mut files = vec![output_png_path.clone()];
+        if let Some(label_page_path) = label_layout
+            .as_ref()
+            .and_then(|label_layout| label_layout.label_page_path(&output_png_path))
+        {
+            files.push(label_page_path);
+        }
+        if has_atlas {
+            files.push(file_manager.atlas_target(&dir_name));
+        }
+        for skeleton_file in &skeletons {
+            files.push(file_manager.skeleton_target(&dir_name, skeleton_file.format));
+        }
+        let files: Vec<String> = files
+            .iter()
+            .map(|file| manifest::relative_path(output_dir, file))
+            .collect();
+        manifest.add_variant(&number_text, &files);
+
+        // 哈希由原骨骼、图集和本变体的图片内容决定，同样的输入总是得到同样的哈希
+        if args.unique_hash && has_skel {
+            let png_bytes = fs::read(&output_png_path)?;
+            let atlas_text = atlas
+                .as_ref()
+                .map(|atlas| atlas.to_text())
+                .unwrap_or_default();
+            for skeleton_file in &skeletons {
+                let original = fs::read(&skeleton_file.path)?;
+                let content_hash =
+                    skeleton::content_hash(&[&original, atlas_text.as_bytes(), &png_bytes]);
+                let target = file_manager.skeleton_target(&dir_name, skeleton_file.format);
+                let hash = skeleton::rewrite_hash(&target, skeleton_file.format, content_hash)?;
+                println!("Skeleton hash of {}: {}", target, hash);
+            }
+        }
     }
 
+    let manifest_path = manifest.save(output_dir)?;
+    println!("Manifest written to {}", manifest_path);
+
     if has_atlas || has_skel {
-        println!(
-            "Processing completed! Generated {} directories.",
-            args.count
-        );
+        println!("Processing completed! Generated {} directories.", count);
     } else {
         println!(
             "Processing completed! Generated {} image files in output directory.",
-            args.count
+            count
         );
     }
     Ok(())
 }
+
+/// 按文字矩形设置绘制的裁剪范围和网格遮罩，生成和 GUI 预览共用
+struct LabelClip {
+    atlas: Atlas,
+    png_path: String,
+    skeletons: Vec<SkeletonFile>,
+    region_padding: u32,
+    clip_to_mesh: bool,
+}
+
+impl LabelClip {
+    fn new(args: &Args, atlas: &Atlas, png_path: &str, skeletons: &[SkeletonFile]) -> Self {
+        Self {
+            atlas: atlas.clone(),
+            png_path: png_path.to_string(),
+            skeletons: skeletons.to_vec(),
+            region_padding: args.region_padding,
+            clip_to_mesh: args.clip_to_mesh,
+        }
+    }
+
+    /// 把绘制限制在文字矩形所在的区域内，并设置网格的可见范围；report 为 false 时不输出提示
+    fn apply(
+        &self,
+        image_processor: &mut ImageProcessor,
+        rect: &gui::Rect,
+        report: bool,
+    ) -> Result<()> {
+        image_processor.set_clip_bounds(region_clip_bounds(
+            &self.atlas,
+            &self.png_path,
+            rect,
+            self.region_padding,
+            report,
+        ));
+
+        // 只有 --clip-to-mesh 依赖网格，其余情况下读不出网格只影响可见性提示
+        let mask =
+            match mesh_visible_mask(&self.atlas, &self.png_path, rect, &self.skeletons, report) {
+                Ok(mask) => mask,
+                Err(error) if !self.clip_to_mesh => {
+                    if report {
+                        println!(
+                            "Warning: cannot check the mesh visibility of the label: {:#}",
+                            error
+                        );
+                    }
+                    None
+                }
+                Err(error) => return Err(error),
+            };
+        if report && mask.is_none() && self.clip_to_mesh {
+            println!("Warning: no mesh in the .json skeleton uses the label region, --clip-to-mesh has no effect");
+        }
+        image_processor.set_visible_mask(mask, self.clip_to_mesh);
+        Ok(())
+    }
+}
+
+/// 按 --pma 判断图片是否为预乘透明度；auto 时读取图集纸张的 pma 字段，没有对应纸张时根据像素判断
+fn is_premultiplied(
+    pma: PmaMode,
+    atlas: Option<&Atlas>,
+    png_path: &str,
+    image_processor: &ImageProcessor,
+) -> bool {
+    match pma {
+        PmaMode::On => true,
+        PmaMode::Off => false,
+        PmaMode::Auto => {
+            let page_pma = atlas
+                .and_then(|atlas| atlas.page_for_image(png_path))
+                .map(|page| page.pma);
+            match page_pma {
+                Some(pma) => pma,
+                None => image_processor.looks_premultiplied(),
+            }
+        }
+    }
+}
+
+/// 找到文字矩形所在的图集区域，返回该区域减去内边距后的裁剪范围
+///
+/// report 为 true 时提示矩形没有落在区域内或超出了区域。
+fn region_clip_bounds(
+    atlas: &Atlas,
+    png_path: &str,
+    rect: &gui::Rect,
+    padding: u32,
+    report: bool,
+) -> Option<PixelRect> {
+    let label_bounds = PixelRect::new(rect.x, rect.y, rect.width, rect.height);
+
+    let Some(page) = atlas.page_for_image(png_path) else {
+        if report {
+            println!(
+                "Warning: no atlas page matches {}, drawing is only clipped to the selected rectangle",
+                png_path
+            );
+        }
+        return None;
+    };
+
+    let Some(region) = page.region_containing(&label_bounds) else {
+        if report {
+            println!("Warning: the selected rectangle does not overlap any atlas region, drawing is only clipped to the selected rectangle");
+        }
+        return None;
+    };
+
+    let clip_bounds = region.packed_bounds().inset(padding);
+    if report && !clip_bounds.contains_rect(&label_bounds) {
+        println!(
+            "Warning: the selected rectangle extends beyond atlas region '{}' (x={}, y={}, width={}, height={}), drawing will be clipped",
+            region.name, clip_bounds.x, clip_bounds.y, clip_bounds.width, clip_bounds.height
+        );
+    }
+
+    Some(clip_bounds)
+}
+
+/// 通过 --slot 或 --attachment 在骨骼的皮肤中找到附件使用的图集区域
+///
+/// 同一个附件在不同皮肤中使用不同区域时全部列出，默认使用初始姿势（默认皮肤）中的区域，
+/// 也可以用 --region 从中选择。
+fn skeleton_target_region(args: &Args, skeletons: &[SkeletonFile]) -> Result<String> {
+    // JSON 骨骼支持的版本更多，优先使用
+    let skeleton_file = skeletons
+        .iter()
+        .find(|skeleton_file| skeleton_file.format == SkeletonFormat::Json)
+        .or(skeletons.first())
+        .ok_or_else(|| {
+            anyhow::anyhow!("--slot and --attachment require a .skel or .json skeleton")
+        })?;
+    let skins = skeleton_file.read_skins()?;
+
+    let (description, attachments) = match (&args.slot, &args.attachment) {
+        (Some(slot), _) => (format!("slot '{}'", slot), skins.slot_attachments(slot)?),
+        (None, Some(attachment)) => (
+            format!("attachment '{}'", attachment),
+            skins.named_attachments(attachment),
+        ),
+        (None, None) => anyhow::bail!("--slot or --attachment is required"),
+    };
+
+    let mut regions: Vec<&str> = Vec::new();
+    for attachment in &attachments {
+        if !regions.contains(&attachment.path.as_str()) {
+            regions.push(&attachment.path);
+        }
+    }
+    let Some(first_region) = regions.first() else {
+        anyhow::bail!(
+            "No skin attachment with an atlas region found for {}",
+            description
+        );
+    };
+
+    if regions.len() > 1 {
+        println!("{} uses {} atlas regions:", description, regions.len());
+        for attachment in &attachments {
+            println!(
+                "  skin '{}', slot '{}', attachment '{}': region '{}'",
+                attachment.skin, attachment.slot, attachment.name, attachment.path
+            );
+        }
+    }
+
+    let region_name = match &args.region {
+        Some(region_name) if regions.contains(&region_name.as_str()) => region_name.clone(),
+        Some(region_name) => anyhow::bail!(
+            "Region '{}' is not used by {}, choose one of: {}",
+            region_name,
+            description,
+            regions.join(", ")
+        ),
+        None => first_region.to_string(),
+    };
+
+    if regions.len() > 1 && args.region.is_none() {
+        println!(
+            "Using region '{}', pass --region to choose another",
+            region_name
+        );
+    } else {
+        println!("{} uses atlas region '{}'", description, region_name);
+    }
+    Ok(region_name)
+}
+
+/// 有 JSON 骨骼和图集时，为GUI准备初始姿势预览；准备失败时只给出警告
+fn setup_pose_preview(
+    args: &Args,
+    skeletons: &[SkeletonFile],
+    atlas: Option<&Atlas>,
+    png_path: &str,
+    count: u32,
+    fonts: &FontStack,
+) -> Option<gui::PreviewSource> {
+    let skeleton_file = skeletons
+        .iter()
+        .find(|skeleton_file| skeleton_file.format == SkeletonFormat::Json)?;
+    let atlas = atlas?;
+
+    let source = (|| -> Result<gui::PreviewSource> {
+        let text = fs::read_to_string(&skeleton_file.path)?;
+        let pose = preview::SetupPose::from_json(&text)?;
+        let label_page = atlas
+            .page_for_image(png_path)
+            .ok_or_else(|| anyhow::anyhow!("No atlas page matches {}", png_path))?
+            .name
+            .clone();
+
+        // 纸张名相对于图集所在目录
+        let atlas_dir = Path::new(png_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let mut pages = HashMap::new();
+        for page in &atlas.pages {
+            let image = image::open(atlas_dir.join(&page.name))
+                .with_context(|| format!("Failed to read atlas page: {}", page.name))?;
+            pages.insert(page.name.clone(), image.to_rgba8());
+        }
+
+        // 与生成时相同的文字设置，裁剪范围和网格遮罩随选择的矩形更新
+        let mut image_processor = ImageProcessor::new(png_path);
+        image_processor.set_text_layout(text_layout(args));
+        image_processor.set_fonts(fonts.clone());
+        let premultiplied = is_premultiplied(args.pma, Some(atlas), png_path, &image_processor);
+        image_processor.set_premultiplied(premultiplied);
+        let label_clip = LabelClip::new(args, atlas, png_path, skeletons);
+
+        Ok(gui::PreviewSource {
+            pose,
+            atlas: atlas.clone(),
+            pages,
+            label_page,
+            image_processor,
+            fonts: fonts.clone(),
+            label_text: merge::label_text(&args.label_text, &merge::variant_number(1, count)),
+            apply_clip: Box::new(
+                move |rect: &gui::Rect, image_processor: &mut ImageProcessor| {
+                    label_clip.apply(image_processor, rect, false)
+                },
+            ),
+        })
+    })();
+
+    match source {
+        Ok(source) => Some(source),
+        Err(error) => {
+            println!("Warning: setup pose preview is unavailable: {:#}", error);
+            None
+        }
+    }
+}
+
+/// 文字所在区域被 JSON 骨骼中的网格使用时，返回网格三角形在纸张上覆盖的像素
+///
+/// 网格只显示三角形覆盖的部分，区域内其余像素在游戏中看不到。report 为 true 时输出使用该区域的网格，
+/// 文字矩形没有完全落在其中时给出警告。
+fn mesh_visible_mask(
+    atlas: &Atlas,
+    png_path: &str,
+    rect: &gui::Rect,
+    skeletons: &[SkeletonFile],
+    report: bool,
+) -> Result<Option<PixelMask>> {
+    let Some(skeleton_file) = skeletons
+        .iter()
+        .find(|skeleton_file| skeleton_file.format == SkeletonFormat::Json)
+    else {
+        return Ok(None);
+    };
+    let label_bounds = PixelRect::new(rect.x, rect.y, rect.width, rect.height);
+    let Some(region) = atlas
+        .page_for_image(png_path)
+        .and_then(|page| page.region_containing(&label_bounds))
+    else {
+        return Ok(None);
+    };
+
+    let text = fs::read_to_string(&skeleton_file.path)?;
+    let meshes = skeleton::json::read_meshes(&text, &region.name)
+        .with_context(|| format!("Failed to read meshes: {}", skeleton_file.path))?;
+    if meshes.is_empty() {
+        return Ok(None);
+    }
+
+    // 纹理坐标相对于区域的原始图片，先换算成原始图片像素再映射到纸张
+    let mut mask = PixelMask::new(region.packed_bounds());
+    for mesh in &meshes {
+        let point = |index: usize| {
+            region.original_point_to_page(
+                mesh.uvs[index * 2] * region.original_width as f32,
+                mesh.uvs[index * 2 + 1] * region.original_height as f32,
+            )
+        };
+        for triangle in mesh.triangles.chunks_exact(3) {
+            mask.fill_triangle(point(triangle[0]), point(triangle[1]), point(triangle[2]));
+        }
+    }
+
+    if !report {
+        return Ok(Some(mask));
+    }
+
+    let visible = mask.count_in(&label_bounds);
+    let total = label_bounds.area();
+    println!(
+        "Region '{}' is drawn by {} mesh attachment(s), {:.0}% of the label rectangle is visible",
+        region.name,
+        meshes.len(),
+        visible as f64 * 100.0 / total.max(1) as f64
+    );
+    for mesh in &meshes {
+        println!(
+            "  mesh '{}' in slot '{}' (skin '{}')",
+            mesh.name, mesh.slot, mesh.skin
+        );
+    }
+    if visible == 0 {
+        println!(
+            "Warning: the label rectangle lies entirely outside the visible mesh triangles of region '{}'",
+            region.name
+        );
+    } else if visible < total {
+        println!(
+            "Warning: part of the label rectangle lies outside the visible mesh triangles of region '{}'",
+            region.name
+        );
+    }
+
+    Ok(Some(mask))
+}
+
+/// 根据命令行参数构造文字矩形
+///
+/// 指定了区域时，矩形是相对于区域原始图片的坐标，没有矩形时覆盖整个区域。
+fn rect_from_args(
+    args: &Args,
+    label_rect: Option<PixelRect>,
+    region_name: Option<&str>,
+    atlas: Option<&Atlas>,
+    png_path: &str,
+) -> Result<gui::Rect> {
+    // 指定角度时以正常方向为基准旋转
+    let mut text_direction = if args.angle.is_some() {
+        TextDirection::Down
+    } else {
+        args.direction
+    };
+
+    let page_rect = if let Some(region_name) = region_name {
+        let atlas = atlas.ok_or_else(|| {
+            anyhow::anyhow!("Placing the label in an atlas region requires an .atlas file")
+        })?;
+        let page = atlas
+            .page_for_image(png_path)
+            .ok_or_else(|| anyhow::anyhow!("No atlas page matches {}", png_path))?;
+        let region = page.region(region_name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Atlas region not found on page '{}': {}",
+                page.name,
+                region_name
+            )
+        })?;
+        let label_rect = label_rect.unwrap_or_else(|| region.content_bounds_in_original());
+
+        let page_rect = region.original_rect_to_page(&label_rect).ok_or_else(|| {
+            anyhow::anyhow!(
+                "The rectangle lies entirely in the stripped whitespace of region '{}'",
+                region_name
+            )
+        })?;
+
+        if !region
+            .content_bounds_in_original()
+            .contains_rect(&label_rect)
+        {
+            println!(
+                "Warning: part of the rectangle lies in the stripped whitespace of region '{}' and was clipped away",
+                region_name
+            );
+        }
+
+        // 旋转存放的区域里，文字方向要跟着区域一起旋转
+        text_direction = text_direction.rotated_ccw(region.degrees);
+
+        println!(
+            "Mapped original rectangle of region '{}' to page rectangle: x={}, y={}, width={}, height={}",
+            region_name, page_rect.x, page_rect.y, page_rect.width, page_rect.height
+        );
+        page_rect
+    } else {
+        label_rect.ok_or_else(|| anyhow::anyhow!("--rect is required"))?
+    };
+
+    Ok(gui::Rect {
+        x: page_rect.x,
+        y: page_rect.y,
+        width: page_rect.width,
+        height: page_rect.height,
+        text_color: args.text_color,
+        enable_color_variation: args.color_variation,
+        base_hue: 0.0,
+        text_direction,
+        text_angle: args.angle.unwrap_or(0.0),
+        mirror: args.mirror,
+        effects: text_effects(args),
+        font: font_choice(args),
+    })
+}
+
+/// 命令行指定的字体
+fn font_choice(args: &Args) -> Option<FontChoice> {
+    args.font.as_ref().map(|path| FontChoice {
+        path: path.clone(),
+        index: args.font_index,
+    })
+}
+
+/// 命令行指定的描边和投影
+fn text_effects(args: &Args) -> TextEffects {
+    TextEffects {
+        outline: args.outline.then_some(Outline {
+            width: args.outline_width,
+            color: args.outline_color,
+            join: args.outline_join,
+        }),
+        shadow: args.shadow.then_some(Shadow {
+            offset_x: args.shadow_offset.0,
+            offset_y: args.shadow_offset.1,
+            blur: args.shadow_blur,
+            color: args.shadow_color,
+            opacity: args.shadow_opacity,
+        }),
+    }
+}
+
+/// 命令行指定的多行排版方式
+fn text_layout(args: &Args) -> TextLayout {
+    TextLayout {
+        padding: args.text_padding,
+        anchor: args.text_anchor,
+        line_spacing: args.line_spacing,
+        horizontal_align: args.align,
+        vertical_align: args.vertical_align,
+        word_wrap: args.wrap,
+    }
+}
+
+/// 解析 x,y 格式的偏移
+fn parse_offset(value: &str) -> Result<(f32, f32), String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| "expected x,y".to_string())?;
+    let number = |part: &str| {
+        part.trim()
+            .parse::<f32>()
+            .map_err(|_| format!("invalid offset: {}", value))
+    };
+    Ok((number(x)?, number(y)?))
+}
+
+/// 解析 #RRGGBB 格式的颜色
+fn parse_color(value: &str) -> Result<egui::Color32, String> {
+    let hex = value.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err("expected #RRGGBB".to_string());
+    }
+
+    let channel = |range: std::ops::Range<usize>| {
+        u8::from_str_radix(&hex[range], 16).map_err(|_| format!("invalid color: {}", value))
+    };
+    Ok(egui::Color32::from_rgb(
+        channel(0..2)?,
+        channel(2..4)?,
+        channel(4..6)?,
+    ))
+}
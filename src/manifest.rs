@@ -0,0 +1,63 @@
+use crate::font::{FontStack, LoadedFont};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// 输出目录中的 manifest.json：记录输入、所用字体以及每个变体的文件
+pub struct Manifest {
+    source: String,
+    font: Value,
+    fallback_fonts: Vec<Value>,
+    variants: Vec<Value>,
+}
+
+impl Manifest {
+    pub fn new(source: &str, fonts: &FontStack) -> Self {
+        Self {
+            source: source.to_string(),
+            font: font_json(fonts.primary()),
+            fallback_fonts: fonts.fallbacks().iter().map(font_json).collect(),
+            variants: Vec::new(),
+        }
+    }
+
+    /// 记录一个变体；files 为相对于输出目录的路径
+    pub fn add_variant(&mut self, label: &str, files: &[String]) {
+        self.variants.push(json!({
+            "label": label,
+            "files": files,
+        }));
+    }
+
+    /// 写出 manifest.json，返回其路径
+    pub fn save(&self, output_dir: &str) -> Result<String> {
+        let manifest = json!({
+            "source": self.source,
+            "font": self.font,
+            "fallback_fonts": self.fallback_fonts,
+            "variants": self.variants,
+        });
+        let path = Path::new(output_dir).join("manifest.json");
+        fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))?;
+        Ok(path.to_string_lossy().to_string())
+    }
+}
+
+fn font_json(font: &LoadedFont) -> Value {
+    json!({
+        "path": font.choice.path,
+        "index": font.choice.index,
+        "faces": font.face_count,
+    })
+}
+
+/// 输出目录下文件的相对路径，统一使用 `/` 分隔
+pub fn relative_path(output_dir: &str, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(output_dir)
+        .unwrap_or_else(|_| Path::new(path))
+        .to_string_lossy()
+        .replace('\\', "/")
+}
@@ -0,0 +1,345 @@
+use crate::atlas::Atlas;
+use crate::geometry::PixelRect;
+use crate::gui::Rect;
+use crate::image_processor::ImageProcessor;
+use crate::manifest::{self, Manifest};
+use crate::skeleton::{json, SkeletonFile, SkeletonFormat};
+use anyhow::{Context, Result};
+use image::{imageops, DynamicImage, RgbaImage};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// 标签区域之间以及与纸张边缘之间留出的透明像素，避免线性过滤时相互渗色
+pub const PACK_PADDING: u32 = 2;
+
+/// 合并输出需要的输入
+pub struct MergeInput<'a> {
+    pub spine_path: &'a str,
+    pub spine_name: &'a str,
+    pub png_path: &'a str,
+    pub output_dir: &'a str,
+    pub count: u32,
+    pub atlas: &'a Atlas,
+    pub skeletons: &'a [SkeletonFile],
+    pub rect: &'a Rect,
+    /// 标签文字模板，`{n}` 为变体编号
+    pub label_template: &'a str,
+}
+
+/// 把所有变体合并到一个骨骼中：每个变体是一个皮肤，指向新增的标签区域
+///
+/// 原纸张保持不变，每个变体只把文字所在区域的像素打包到新增的纸张上，
+/// 图集中为其登记新区域，骨骼 JSON 中为其新增皮肤。
+pub fn run(
+    input: &MergeInput,
+    image_processor: &ImageProcessor,
+    manifest: &mut Manifest,
+) -> Result<()> {
+    let skeleton_file = input
+        .skeletons
+        .iter()
+        .find(|skeleton| skeleton.format == SkeletonFormat::Json)
+        .ok_or_else(|| anyhow::anyhow!("--merge-skins requires a .json skeleton"))?;
+    if input
+        .skeletons
+        .iter()
+        .any(|skeleton| skeleton.format == SkeletonFormat::Binary)
+    {
+        println!("Warning: binary skeletons cannot be merged, only the .json skeleton is written");
+    }
+
+    let page = input
+        .atlas
+        .page_for_image(input.png_path)
+        .ok_or_else(|| anyhow::anyhow!("No atlas page matches {}", input.png_path))?;
+    let label_bounds = PixelRect::new(
+        input.rect.x,
+        input.rect.y,
+        input.rect.width,
+        input.rect.height,
+    );
+    let region = page
+        .region_containing(&label_bounds)
+        .ok_or_else(|| anyhow::anyhow!("The label rectangle does not overlap any atlas region"))?
+        .clone();
+    println!("Merging label variants of region '{}'", region.name);
+
+    let text = fs::read_to_string(&skeleton_file.path)
+        .with_context(|| format!("Failed to read skeleton file: {}", skeleton_file.path))?;
+    let mut skeleton: Value = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid skeleton JSON: {}", skeleton_file.path))?;
+    let attachments = region_attachments(&skeleton, &region.name)?;
+    if attachments.is_empty() {
+        anyhow::bail!(
+            "No attachment in the default skin uses region '{}'",
+            region.name
+        );
+    }
+
+    let mut atlas = input.atlas.clone();
+    let region_bounds = region.packed_bounds();
+    let layout = GridLayout::new(
+        region_bounds.width,
+        region_bounds.height,
+        page.width,
+        page.height,
+        input.count,
+    );
+    let mut label_pages: Vec<(String, RgbaImage)> = Vec::new();
+
+    for i in 1..=input.count {
+        let number_text = variant_number(i, input.count);
+
+        let (rendered, report) = image_processor.render_text_in_rect(
+            &label_text(input.label_template, &number_text),
+            input.rect,
+            input.rect.enable_color_variation,
+            0.0,
+            i,
+        )?;
+        if report.clipped_pixels > 0 {
+            println!(
+                "Warning: {} text pixels of variant {} fell outside the clip area and were discarded",
+                report.clipped_pixels, number_text
+            );
+        }
+        if report.hidden_pixels > 0 {
+            println!(
+                "Warning: {} text pixels of variant {} lie outside the visible mesh triangles",
+                report.hidden_pixels, number_text
+            );
+        }
+
+        let (page_index, x, y) = layout.position(i - 1);
+        if page_index == label_pages.len() {
+            let page_name = label_page_name(input.spine_name, page_index);
+            let (width, height) = layout.page_size(page_index);
+            atlas.add_page(&page_name, width, height)?;
+            label_pages.push((page_name, RgbaImage::new(width, height)));
+        }
+
+        let crop = imageops::crop_imm(
+            &rendered,
+            region_bounds.x as u32,
+            region_bounds.y as u32,
+            region_bounds.width,
+            region_bounds.height,
+        )
+        .to_image();
+        let (page_name, page_image) = &mut label_pages[page_index];
+        imageops::replace(page_image, &crop, x as i64, y as i64);
+
+        // 新区域沿用原区域的旋转、偏移和原始尺寸，附件因此不需要调整
+        let mut label_region = region.clone();
+        label_region.name = format!("{}_{}", region.name, number_text);
+        label_region.x = x as i32;
+        label_region.y = y as i32;
+        label_region.index = -1;
+        atlas.add_region(page_name, &label_region)?;
+
+        add_skin(
+            &mut skeleton,
+            &number_text,
+            &attachments,
+            &label_region.name,
+        )?;
+    }
+
+    fs::create_dir_all(input.output_dir)?;
+
+    let atlas_target = format!("{}/{}.atlas", input.output_dir, input.spine_name);
+    atlas.save(&atlas_target)?;
+
+    let skeleton_target = format!("{}/{}.json", input.output_dir, input.spine_name);
+    let output = serde_json::to_string_pretty(&skeleton)?;
+    fs::write(&skeleton_target, output)
+        .with_context(|| format!("Failed to write skeleton file: {}", skeleton_target))?;
+
+    // 原有纸张原样复制，图集中的纸张名相对于图集所在目录
+    let source_dir = Path::new(input.spine_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    for page in &input.atlas.pages {
+        let target = Path::new(input.output_dir).join(&page.name);
+        fs::copy(source_dir.join(&page.name), &target)
+            .with_context(|| format!("Failed to copy atlas page: {}", page.name))?;
+    }
+
+    for (page_name, page_image) in &label_pages {
+        let target = Path::new(input.output_dir).join(page_name);
+        DynamicImage::ImageRgba8(page_image.clone()).save(&target)?;
+    }
+
+    // 所有皮肤共用同一套骨骼、图集和纸张
+    let mut files = vec![
+        manifest::relative_path(input.output_dir, &skeleton_target),
+        manifest::relative_path(input.output_dir, &atlas_target),
+    ];
+    files.extend(input.atlas.pages.iter().map(|page| page.name.clone()));
+    files.extend(label_pages.iter().map(|(page_name, _)| page_name.clone()));
+    for i in 1..=input.count {
+        manifest.add_variant(&variant_number(i, input.count), &files);
+    }
+
+    println!(
+        "Merged {} variants into {} as skins {} to {}, label regions packed onto {} extra page(s).",
+        input.count,
+        skeleton_target,
+        variant_number(1, input.count),
+        variant_number(input.count, input.count),
+        layout.page_count()
+    );
+    Ok(())
+}
+
+/// 与目录模式相同的编号：超过99个使用3位数字，否则使用2位数字
+pub fn variant_number(i: u32, count: u32) -> String {
+    if count > 99 {
+        format!("{:03}", i)
+    } else {
+        format!("{:02}", i)
+    }
+}
+
+/// 展开标签文字模板：`{n}` 为变体编号，`\n` 为换行
+pub fn label_text(template: &str, number: &str) -> String {
+    template.replace("\\n", "\n").replace("{n}", number)
+}
+
+/// 标签纸张命名为 `<名称>_labels.png`、`<名称>_labels2.png`……
+fn label_page_name(spine_name: &str, page_index: usize) -> String {
+    if page_index == 0 {
+        format!("{}_labels.png", spine_name)
+    } else {
+        format!("{}_labels{}.png", spine_name, page_index + 1)
+    }
+}
+
+/// 所有标签区域大小相同，按网格排列到若干张纸上
+struct GridLayout {
+    cell_width: u32,
+    cell_height: u32,
+    columns: u32,
+    rows: u32,
+    count: u32,
+}
+
+impl GridLayout {
+    /// 每张纸不超过原纸张的大小（区域本身更大时除外）
+    fn new(cell_width: u32, cell_height: u32, max_width: u32, max_height: u32, count: u32) -> Self {
+        let fit =
+            |max: u32, cell: u32| (max.saturating_sub(PACK_PADDING) / (cell + PACK_PADDING)).max(1);
+        Self {
+            cell_width,
+            cell_height,
+            columns: fit(max_width, cell_width),
+            rows: fit(max_height, cell_height),
+            count,
+        }
+    }
+
+    fn per_page(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    fn page_count(&self) -> usize {
+        self.count.div_ceil(self.per_page()) as usize
+    }
+
+    /// 第 index 个区域所在的纸张序号和左上角坐标
+    fn position(&self, index: u32) -> (usize, u32, u32) {
+        let page_index = index / self.per_page();
+        let cell = index % self.per_page();
+        let x = PACK_PADDING + (cell % self.columns) * (self.cell_width + PACK_PADDING);
+        let y = PACK_PADDING + (cell / self.columns) * (self.cell_height + PACK_PADDING);
+        (page_index as usize, x, y)
+    }
+
+    /// 最后一张纸只保留实际用到的行列
+    fn page_size(&self, page_index: usize) -> (u32, u32) {
+        let used = (self.count - page_index as u32 * self.per_page()).min(self.per_page());
+        let columns = used.min(self.columns);
+        let rows = used.div_ceil(self.columns);
+        (
+            PACK_PADDING + columns * (self.cell_width + PACK_PADDING),
+            PACK_PADDING + rows * (self.cell_height + PACK_PADDING),
+        )
+    }
+}
+
+/// 默认皮肤中使用该区域的附件，返回 (插槽名, 附件名, 附件数据)
+fn region_attachments(skeleton: &Value, region_name: &str) -> Result<Vec<(String, String, Value)>> {
+    let (_, default_skin) = json::skin_list(skeleton)
+        .into_iter()
+        .find(|(name, _)| *name == "default")
+        .ok_or_else(|| anyhow::anyhow!("Skeleton JSON has no default skin"))?;
+
+    let mut attachments = Vec::new();
+    for (slot_name, slot_attachments) in default_skin {
+        let Some(slot_attachments) = slot_attachments.as_object() else {
+            continue;
+        };
+        for (attachment_name, attachment) in slot_attachments {
+            // 没有 path 时区域名就是附件名
+            let path = attachment
+                .get("path")
+                .and_then(Value::as_str)
+                .unwrap_or(attachment_name);
+            if path == region_name {
+                attachments.push((
+                    slot_name.clone(),
+                    attachment_name.clone(),
+                    attachment.clone(),
+                ));
+            }
+        }
+    }
+    Ok(attachments)
+}
+
+/// 新增一个皮肤，其中的附件与默认皮肤同名，只把 path 指向新的区域
+fn add_skin(
+    skeleton: &mut Value,
+    skin_name: &str,
+    attachments: &[(String, String, Value)],
+    region_name: &str,
+) -> Result<()> {
+    let mut skin_attachments = Map::new();
+    for (slot_name, attachment_name, attachment) in attachments {
+        let mut attachment = attachment.clone();
+        if let Some(attachment) = attachment.as_object_mut() {
+            attachment.insert("path".to_string(), Value::from(region_name));
+        }
+        if let Some(slot) = skin_attachments
+            .entry(slot_name.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+        {
+            slot.insert(attachment_name.clone(), attachment);
+        }
+    }
+
+    match skeleton.get_mut("skins") {
+        Some(Value::Array(skins)) => {
+            if skins
+                .iter()
+                .any(|skin| skin.get("name").and_then(Value::as_str) == Some(skin_name))
+            {
+                anyhow::bail!("Skeleton already has a skin named '{}'", skin_name);
+            }
+            let mut skin = Map::new();
+            skin.insert("name".to_string(), Value::from(skin_name));
+            skin.insert("attachments".to_string(), Value::Object(skin_attachments));
+            skins.push(Value::Object(skin));
+        }
+        Some(Value::Object(skins)) => {
+            if skins.contains_key(skin_name) {
+                anyhow::bail!("Skeleton already has a skin named '{}'", skin_name);
+            }
+            skins.insert(skin_name.to_string(), Value::Object(skin_attachments));
+        }
+        _ => anyhow::bail!("Skeleton JSON has no skins"),
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use crate::atlas::Atlas;
+use crate::image_processor::blend_over;
+use crate::skeleton::json;
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 骨骼在初始姿势下的世界变换
+#[derive(Debug, Clone, Copy)]
+struct BoneTransform {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    world_x: f32,
+    world_y: f32,
+}
+
+impl BoneTransform {
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.b * y + self.world_x,
+            self.c * x + self.d * y + self.world_y,
+        )
+    }
+}
+
+/// 一个区域附件在世界坐标中的平行四边形
+///
+/// 原始图片 (u, v) 处（归一化，v 轴向下）的世界坐标为 origin + u_axis * u + v_axis * v。
+#[derive(Debug, Clone)]
+struct RegionDraw {
+    region: String,
+    origin: (f32, f32),
+    u_axis: (f32, f32),
+    v_axis: (f32, f32),
+    /// 插槽颜色与附件颜色相乘的结果
+    color: [f32; 4],
+}
+
+/// JSON 骨骼的初始姿势，按插槽顺序保存要绘制的区域附件
+#[derive(Debug, Clone)]
+pub struct SetupPose {
+    draws: Vec<RegionDraw>,
+    /// 初始姿势中显示、但不是区域附件（网格等）而没有绘制的附件数量
+    pub skipped: usize,
+}
+
+impl SetupPose {
+    /// 计算所有骨骼的世界变换，收集默认皮肤中各插槽初始显示的区域附件
+    ///
+    /// 只实现普通的变换继承方式，其他继承方式按普通方式近似。
+    pub fn from_json(text: &str) -> Result<Self> {
+        let root: Value = serde_json::from_str(text).context("Invalid skeleton JSON")?;
+        let number = |value: &Value, key: &str, default: f32| {
+            value
+                .get(key)
+                .and_then(Value::as_f64)
+                .map_or(default, |v| v as f32)
+        };
+
+        let mut bones: HashMap<String, BoneTransform> = HashMap::new();
+        for bone in root
+            .get("bones")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = bone.get("name").and_then(Value::as_str).unwrap_or_default();
+            let rotation = number(bone, "rotation", 0.0);
+            let shear_x = number(bone, "shearX", 0.0);
+            let shear_y = number(bone, "shearY", 0.0);
+            let scale_x = number(bone, "scaleX", 1.0);
+            let scale_y = number(bone, "scaleY", 1.0);
+            let x = number(bone, "x", 0.0);
+            let y = number(bone, "y", 0.0);
+
+            let rotation_x = (rotation + shear_x).to_radians();
+            let rotation_y = (rotation + 90.0 + shear_y).to_radians();
+            let la = rotation_x.cos() * scale_x;
+            let lb = rotation_y.cos() * scale_y;
+            let lc = rotation_x.sin() * scale_x;
+            let ld = rotation_y.sin() * scale_y;
+
+            // 骨骼按父骨骼在前的顺序排列
+            let parent = bone
+                .get("parent")
+                .and_then(Value::as_str)
+                .and_then(|parent| bones.get(parent));
+            let transform = match parent {
+                Some(p) => BoneTransform {
+                    a: p.a * la + p.b * lc,
+                    b: p.a * lb + p.b * ld,
+                    c: p.c * la + p.d * lc,
+                    d: p.c * lb + p.d * ld,
+                    world_x: p.a * x + p.b * y + p.world_x,
+                    world_y: p.c * x + p.d * y + p.world_y,
+                },
+                None => BoneTransform {
+                    a: la,
+                    b: lb,
+                    c: lc,
+                    d: ld,
+                    world_x: x,
+                    world_y: y,
+                },
+            };
+            bones.insert(name.to_string(), transform);
+        }
+
+        let default_skin = json::skin_list(&root)
+            .into_iter()
+            .find(|(name, _)| *name == "default")
+            .map(|(_, skin)| skin);
+
+        let mut draws = Vec::new();
+        let mut skipped = 0;
+        for slot in root
+            .get("slots")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let Some(attachment_key) = slot.get("attachment").and_then(Value::as_str) else {
+                continue;
+            };
+            let slot_name = slot.get("name").and_then(Value::as_str).unwrap_or_default();
+            let Some(attachment) = default_skin
+                .and_then(|skin| skin.get(slot_name))
+                .and_then(|attachments| attachments.get(attachment_key))
+            else {
+                continue;
+            };
+            let kind = attachment
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("region");
+            if kind != "region" {
+                skipped += 1;
+                continue;
+            }
+            let Some(bone) = slot
+                .get("bone")
+                .and_then(Value::as_str)
+                .and_then(|bone| bones.get(bone))
+            else {
+                continue;
+            };
+
+            let name = attachment
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or(attachment_key);
+            let region = attachment
+                .get("path")
+                .and_then(Value::as_str)
+                .unwrap_or(name);
+
+            // 附件坐标系：原始图片中心位于 (x, y)，旋转 rotation 度，y 轴向上
+            let width = number(attachment, "width", 0.0) * number(attachment, "scaleX", 1.0);
+            let height = number(attachment, "height", 0.0) * number(attachment, "scaleY", 1.0);
+            let radians = number(attachment, "rotation", 0.0).to_radians();
+            let (sin, cos) = radians.sin_cos();
+            let x = number(attachment, "x", 0.0);
+            let y = number(attachment, "y", 0.0);
+            let local = |u: f32, v: f32| {
+                let px = (u - 0.5) * width;
+                let py = (0.5 - v) * height;
+                bone.apply(x + px * cos - py * sin, y + px * sin + py * cos)
+            };
+            let origin = local(0.0, 0.0);
+            let right = local(1.0, 0.0);
+            let bottom = local(0.0, 1.0);
+
+            let slot_color = parse_color(slot.get("color"));
+            let attachment_color = parse_color(attachment.get("color"));
+            let mut color = [0.0; 4];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel = slot_color[i] * attachment_color[i];
+            }
+
+            draws.push(RegionDraw {
+                region: region.to_string(),
+                origin,
+                u_axis: (right.0 - origin.0, right.1 - origin.1),
+                v_axis: (bottom.0 - origin.0, bottom.1 - origin.1),
+                color,
+            });
+        }
+
+        Ok(Self { draws, skipped })
+    }
+
+    /// 在 CPU 上按插槽顺序绘制初始姿势，结果缩放到不超过 max_size 见方
+    ///
+    /// pages 是以纸张名为键的纸张图片，预乘透明度的纸张按图集的 pma 字段还原；
+    /// 找不到区域或纸张的附件会被跳过。
+    pub fn render(
+        &self,
+        atlas: &Atlas,
+        pages: &HashMap<String, RgbaImage>,
+        max_size: u32,
+    ) -> RgbaImage {
+        let corners: Vec<(f32, f32)> = self
+            .draws
+            .iter()
+            .flat_map(|draw| {
+                let (ox, oy) = draw.origin;
+                let (ux, uy) = draw.u_axis;
+                let (vx, vy) = draw.v_axis;
+                [
+                    (ox, oy),
+                    (ox + ux, oy + uy),
+                    (ox + vx, oy + vy),
+                    (ox + ux + vx, oy + uy + vy),
+                ]
+            })
+            .collect();
+        if corners.is_empty() {
+            return RgbaImage::new(1, 1);
+        }
+
+        let min_x = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min);
+        let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max);
+        let min_y = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min);
+        let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max);
+        let extent = (max_x - min_x).max(max_y - min_y).max(1.0);
+        let scale = max_size as f32 / extent;
+        let width = (((max_x - min_x) * scale).ceil() as u32).max(1);
+        let height = (((max_y - min_y) * scale).ceil() as u32).max(1);
+
+        // 世界坐标 y 轴向上，图片 y 轴向下
+        let to_image = |(x, y): (f32, f32)| ((x - min_x) * scale, (max_y - y) * scale);
+
+        let mut output = RgbaImage::new(width, height);
+        for draw in &self.draws {
+            let Some((page, region)) = atlas
+                .pages
+                .iter()
+                .find_map(|page| page.region(&draw.region).map(|region| (page, region)))
+            else {
+                continue;
+            };
+            let Some(page_image) = pages.get(&page.name) else {
+                continue;
+            };
+
+            let origin = to_image(draw.origin);
+            let u_end = to_image((draw.origin.0 + draw.u_axis.0, draw.origin.1 + draw.u_axis.1));
+            let v_end = to_image((draw.origin.0 + draw.v_axis.0, draw.origin.1 + draw.v_axis.1));
+            let u_axis = (u_end.0 - origin.0, u_end.1 - origin.1);
+            let v_axis = (v_end.0 - origin.0, v_end.1 - origin.1);
+            let determinant = u_axis.0 * v_axis.1 - u_axis.1 * v_axis.0;
+            if determinant.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let xs = [origin.0, u_end.0, v_end.0, u_end.0 + v_axis.0];
+            let ys = [origin.1, u_end.1, v_end.1, u_end.1 + v_axis.1];
+            let left = xs.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+            let right = (xs.iter().cloned().fold(f32::MIN, f32::max).ceil() as u32).min(width);
+            let top = ys.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+            let bottom = (ys.iter().cloned().fold(f32::MIN, f32::max).ceil() as u32).min(height);
+            let packed = region.packed_bounds();
+
+            for y in top..bottom {
+                for x in left..right {
+                    // 由输出像素中心反求原始图片中的归一化坐标
+                    let dx = x as f32 + 0.5 - origin.0;
+                    let dy = y as f32 + 0.5 - origin.1;
+                    let u = (dx * v_axis.1 - dy * v_axis.0) / determinant;
+                    let v = (u_axis.0 * dy - u_axis.1 * dx) / determinant;
+                    if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                        continue;
+                    }
+
+                    // 取原始图片像素的中心，映射后正好落在纸张像素的中心
+                    let (page_x, page_y) = region.original_point_to_page(
+                        (u * region.original_width as f32).floor() + 0.5,
+                        (v * region.original_height as f32).floor() + 0.5,
+                    );
+                    let (page_x, page_y) = (page_x.floor() as i32, page_y.floor() as i32);
+                    // 落在去除的空白里，或者超出纸张
+                    if !packed.contains(page_x, page_y)
+                        || page_x as u32 >= page_image.width()
+                        || page_y as u32 >= page_image.height()
+                    {
+                        continue;
+                    }
+
+                    let mut source = *page_image.get_pixel(page_x as u32, page_y as u32);
+                    if page.pma && source[3] > 0 {
+                        for channel in 0..3 {
+                            let value = source[channel] as u32 * 255 / source[3] as u32;
+                            source[channel] = value.min(255) as u8;
+                        }
+                    }
+                    blend_over(output.get_pixel_mut(x, y), &source, &draw.color);
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// 解析 RRGGBBAA 颜色为 0..1 的分量，缺省为白色
+fn parse_color(value: Option<&Value>) -> [f32; 4] {
+    let mut color = [1.0; 4];
+    if let Some(hex) = value.and_then(Value::as_str) {
+        for (i, channel) in color.iter_mut().enumerate() {
+            if let Some(byte) = hex
+                .get(i * 2..i * 2 + 2)
+                .and_then(|part| u8::from_str_radix(part, 16).ok())
+            {
+                *channel = byte as f32 / 255.0;
+            }
+        }
+    }
+    color
+}
@@ -0,0 +1,787 @@
+use super::{SkeletonSkins, SkinAttachment};
+use anyhow::{Context, Result};
+use std::fs;
+
+/// 二进制骨骼文件头部信息
+#[derive(Debug, Clone)]
+pub struct SkeletonHeader {
+    pub hash: String,
+    pub version: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// 4.2 起才有
+    pub reference_scale: Option<f32>,
+    /// 以下字段只有导出时勾选了 nonessential 数据才有
+    pub fps: Option<f32>,
+    pub images_path: Option<String>,
+    pub audio_path: Option<String>,
+}
+
+impl SkeletonHeader {
+    pub fn load(skel_path: &str) -> Result<Self> {
+        let data = fs::read(skel_path)
+            .with_context(|| format!("Failed to read skeleton file: {}", skel_path))?;
+        Self::parse(&data)
+            .with_context(|| format!("Failed to parse skeleton header: {}", skel_path))
+    }
+
+    /// 解析头部：4.x 的哈希是 8 字节整数，3.x 的哈希是字符串，先按 4.x 尝试
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        Self::parse_with_length(data).map(|(header, _)| header)
+    }
+
+    /// 解析头部，同时返回头部占用的字节数
+    fn parse_with_length(data: &[u8]) -> Result<(Self, usize)> {
+        if let Ok(header) = Self::parse_spine4(data) {
+            return Ok(header);
+        }
+        Self::parse_spine3(data)
+    }
+
+    /// 主版本号，例如 "4.1.23" 为 4
+    pub fn major_version(&self) -> Option<u32> {
+        self.version.split('.').next()?.parse().ok()
+    }
+
+    fn parse_spine4(data: &[u8]) -> Result<(Self, usize)> {
+        let mut input = BinaryInput::new(data);
+        let hash = input.read_long()?;
+        let version = input.read_string()?.unwrap_or_default();
+        if !version.starts_with("4.") {
+            anyhow::bail!("not a Spine 4.x skeleton");
+        }
+
+        let x = input.read_float()?;
+        let y = input.read_float()?;
+        let width = input.read_float()?;
+        let height = input.read_float()?;
+        let reference_scale = if version_at_least(&version, 4, 2) {
+            Some(input.read_float()?)
+        } else {
+            None
+        };
+
+        let mut header = SkeletonHeader {
+            hash: hash.to_string(),
+            version,
+            x,
+            y,
+            width,
+            height,
+            reference_scale,
+            fps: None,
+            images_path: None,
+            audio_path: None,
+        };
+        header.read_nonessential(&mut input)?;
+        Ok((header, input.position()))
+    }
+
+    fn parse_spine3(data: &[u8]) -> Result<(Self, usize)> {
+        let mut input = BinaryInput::new(data);
+        let hash = input.read_string()?.unwrap_or_default();
+        let version = input.read_string()?.unwrap_or_default();
+        if !version.starts_with("3.") {
+            anyhow::bail!("Unsupported skeleton version: '{}'", version);
+        }
+
+        // 3.8 起才在宽高前写入包围盒的 x/y
+        let has_position = version_at_least(&version, 3, 8);
+        let (x, y) = if has_position {
+            (input.read_float()?, input.read_float()?)
+        } else {
+            (0.0, 0.0)
+        };
+        let width = input.read_float()?;
+        let height = input.read_float()?;
+
+        let mut header = SkeletonHeader {
+            hash,
+            version,
+            x,
+            y,
+            width,
+            height,
+            reference_scale: None,
+            fps: None,
+            images_path: None,
+            audio_path: None,
+        };
+        // 3.8 之前 nonessential 数据的布局各版本不同，不再继续读取
+        if has_position {
+            header.read_nonessential(&mut input)?;
+        }
+        Ok((header, input.position()))
+    }
+
+    fn read_nonessential(&mut self, input: &mut BinaryInput) -> Result<()> {
+        if input.read_bool()? {
+            self.fps = Some(input.read_float()?);
+            self.images_path = input.read_string()?;
+            self.audio_path = input.read_string()?;
+        }
+        Ok(())
+    }
+}
+
+/// 替换二进制骨骼的哈希，返回新的文件内容和写入的哈希文本
+///
+/// 4.x 的哈希是开头的 8 字节整数，直接覆盖；3.x 的哈希是开头的字符串，替换后其余字节原样保留。
+pub fn rewrite_hash(data: &[u8], content_hash: u64) -> Result<(Vec<u8>, String)> {
+    let header = SkeletonHeader::parse(data)?;
+
+    if header.major_version() == Some(4) {
+        let mut output = data.to_vec();
+        output[..8].copy_from_slice(&content_hash.to_be_bytes());
+        return Ok((output, (content_hash as i64).to_string()));
+    }
+
+    let mut input = BinaryInput::new(data);
+    input.read_string()?;
+    let hash_end = input.position();
+
+    let hash = super::encode_hash(content_hash);
+    let mut output = Vec::with_capacity(data.len());
+    write_string(&mut output, &hash);
+    output.extend_from_slice(&data[hash_end..]);
+    Ok((output, hash))
+}
+
+/// 替换 nonessential 数据中的图片目录，返回新的文件内容；没有导出 nonessential 数据时返回 None
+pub fn rewrite_images_path(data: &[u8], images_path: &str) -> Result<Option<Vec<u8>>> {
+    let header = SkeletonHeader::parse(data)?;
+    if header.fps.is_none() {
+        return Ok(None);
+    }
+
+    // 依次跳过哈希、版本、包围盒、referenceScale、nonessential 标记和 fps
+    let mut input = BinaryInput::new(data);
+    if header.major_version() == Some(4) {
+        input.read_long()?;
+    } else {
+        input.read_string()?;
+    }
+    input.read_string()?;
+    input.skip(4 * 4)?;
+    if header.reference_scale.is_some() {
+        input.skip(4)?;
+    }
+    input.skip(1 + 4)?;
+
+    let start = input.position();
+    input.read_string()?;
+    let end = input.position();
+
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..start]);
+    write_string(&mut output, images_path);
+    output.extend_from_slice(&data[end..]);
+    Ok(Some(output))
+}
+
+/// 读取初始姿势的插槽附件和所有皮肤中的附件，支持 3.8 到 4.1 的二进制格式
+///
+/// 皮肤位于骨骼、插槽和各类约束之后，需要依次跳过这些数据；4.2 的布局改为按位标记，暂不支持。
+pub fn read_skins(data: &[u8]) -> Result<SkeletonSkins> {
+    let (header, header_length) = SkeletonHeader::parse_with_length(data)?;
+    let version = (
+        header.major_version().unwrap_or(0),
+        header
+            .version
+            .split('.')
+            .nth(1)
+            .and_then(|minor| minor.parse::<u32>().ok())
+            .unwrap_or(0),
+    );
+    if !((3, 8)..(4, 2)).contains(&version) {
+        anyhow::bail!(
+            "Reading skins from Spine {} binary skeletons is not supported, export a .json skeleton instead",
+            header.version
+        );
+    }
+
+    let spine4 = version.0 == 4;
+    let has_sequences = version >= (4, 1);
+    let nonessential = header.fps.is_some();
+    let mut input = BinaryInput::new(data);
+    input.skip(header_length)?;
+
+    let strings = (0..input.read_varint()?)
+        .map(|_| input.read_string().map(Option::unwrap_or_default))
+        .collect::<Result<Vec<_>>>()?;
+    let mut reader = SkinReader {
+        input,
+        strings,
+        nonessential,
+        has_sequences,
+    };
+    let input = &mut reader.input;
+
+    // 骨骼
+    for bone_index in 0..input.read_varint()? {
+        input.read_string()?;
+        if bone_index > 0 {
+            input.read_varint()?;
+        }
+        // rotation, x, y, scaleX, scaleY, shearX, shearY, length
+        input.skip(8 * 4)?;
+        input.read_varint()?;
+        input.read_bool()?;
+        if nonessential {
+            input.skip(4)?;
+        }
+    }
+
+    // 插槽
+    let mut slots = Vec::new();
+    for _ in 0..reader.input.read_varint()? {
+        let name = reader.input.read_string()?.unwrap_or_default();
+        reader.input.read_varint()?;
+        // color, darkColor
+        reader.input.skip(2 * 4)?;
+        let attachment = reader.read_string_ref()?;
+        reader.input.read_varint()?;
+        slots.push((name, attachment));
+    }
+
+    let input = &mut reader.input;
+
+    // IK 约束
+    for _ in 0..input.read_varint()? {
+        skip_constraint_head(input)?;
+        input.read_varint()?;
+        // mix, softness, bendDirection, compress, stretch, uniform
+        input.skip(4 + 4 + 1 + 1 + 1 + 1)?;
+    }
+
+    // 变换约束：4.x 有 6 个混合值，3.8 有 4 个
+    for _ in 0..input.read_varint()? {
+        skip_constraint_head(input)?;
+        input.read_varint()?;
+        // local, relative, 6 个偏移
+        input.skip(2 + 6 * 4)?;
+        input.skip(if spine4 { 6 * 4 } else { 4 * 4 })?;
+    }
+
+    // 路径约束：4.x 有 3 个混合值，3.8 有 2 个
+    for _ in 0..input.read_varint()? {
+        skip_constraint_head(input)?;
+        // target slot, positionMode, spacingMode, rotateMode
+        for _ in 0..4 {
+            input.read_varint()?;
+        }
+        // offsetRotation, position, spacing
+        input.skip(3 * 4)?;
+        input.skip(if spine4 { 3 * 4 } else { 2 * 4 })?;
+    }
+
+    let mut attachments = Vec::new();
+    reader.read_skin(Some("default"), &slots, &mut attachments)?;
+    for _ in 0..reader.input.read_varint()? {
+        reader.read_skin(None, &slots, &mut attachments)?;
+    }
+
+    Ok(SkeletonSkins { slots, attachments })
+}
+
+/// 约束开头的名称、顺序、skinRequired 和骨骼列表
+fn skip_constraint_head(input: &mut BinaryInput) -> Result<()> {
+    input.read_string()?;
+    input.read_varint()?;
+    input.read_bool()?;
+    for _ in 0..input.read_varint()? {
+        input.read_varint()?;
+    }
+    Ok(())
+}
+
+struct SkinReader<'a> {
+    input: BinaryInput<'a>,
+    strings: Vec<String>,
+    nonessential: bool,
+    has_sequences: bool,
+}
+
+impl SkinReader<'_> {
+    /// 引用字符串表，0 表示 null
+    fn read_string_ref(&mut self) -> Result<Option<String>> {
+        let index = self.input.read_varint()? as usize;
+        if index == 0 {
+            return Ok(None);
+        }
+        self.strings
+            .get(index - 1)
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("string reference {} out of range", index))
+    }
+
+    /// 默认皮肤没有名称和骨骼/约束列表，且插槽数为 0 时整个皮肤不存在
+    fn read_skin(
+        &mut self,
+        default_name: Option<&str>,
+        slots: &[(String, Option<String>)],
+        attachments: &mut Vec<SkinAttachment>,
+    ) -> Result<()> {
+        let skin = match default_name {
+            Some(name) => name.to_string(),
+            None => {
+                let name = self.read_string_ref()?.unwrap_or_default();
+                // 骨骼、IK、变换、路径约束的索引列表
+                for _ in 0..4 {
+                    for _ in 0..self.input.read_varint()? {
+                        self.input.read_varint()?;
+                    }
+                }
+                name
+            }
+        };
+
+        for _ in 0..self.input.read_varint()? {
+            let slot_index = self.input.read_varint()? as usize;
+            let slot = slots
+                .get(slot_index)
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| anyhow::anyhow!("slot index {} out of range", slot_index))?;
+            for _ in 0..self.input.read_varint()? {
+                let key = self.read_string_ref()?.unwrap_or_default();
+                if let Some((name, path)) = self.read_attachment(&key)? {
+                    attachments.push(SkinAttachment {
+                        skin: skin.clone(),
+                        slot: slot.clone(),
+                        key: key.clone(),
+                        name,
+                        path,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取一个附件，使用图集区域（region / mesh / linkedmesh）时返回附件名和区域名
+    fn read_attachment(&mut self, key: &str) -> Result<Option<(String, String)>> {
+        let name = self.read_string_ref()?.unwrap_or_else(|| key.to_string());
+        let attachment_type = self.input.read_byte()?;
+        let nonessential_color = if self.nonessential { 4 } else { 0 };
+
+        let path = match attachment_type {
+            // region
+            0 => {
+                let path = self.read_string_ref()?;
+                // rotation, x, y, scaleX, scaleY, width, height, color
+                self.input.skip(7 * 4 + 4)?;
+                self.skip_sequence()?;
+                Some(path.unwrap_or_else(|| name.clone()))
+            }
+            // boundingbox
+            1 => {
+                let vertex_count = self.input.read_varint()?;
+                self.skip_vertices(vertex_count)?;
+                self.input.skip(nonessential_color)?;
+                None
+            }
+            // mesh
+            2 => {
+                let path = self.read_string_ref()?;
+                self.input.skip(4)?;
+                let vertex_count = self.input.read_varint()?;
+                self.input.skip(vertex_count as usize * 2 * 4)?;
+                self.skip_shorts()?;
+                self.skip_vertices(vertex_count)?;
+                self.input.read_varint()?;
+                self.skip_sequence()?;
+                if self.nonessential {
+                    self.skip_shorts()?;
+                    self.input.skip(2 * 4)?;
+                }
+                Some(path.unwrap_or_else(|| name.clone()))
+            }
+            // linkedmesh
+            3 => {
+                let path = self.read_string_ref()?;
+                self.input.skip(4)?;
+                self.read_string_ref()?;
+                self.read_string_ref()?;
+                self.input.read_bool()?;
+                self.skip_sequence()?;
+                if self.nonessential {
+                    self.input.skip(2 * 4)?;
+                }
+                Some(path.unwrap_or_else(|| name.clone()))
+            }
+            // path
+            4 => {
+                self.input.skip(2)?;
+                let vertex_count = self.input.read_varint()?;
+                self.skip_vertices(vertex_count)?;
+                self.input.skip((vertex_count / 3) as usize * 4)?;
+                self.input.skip(nonessential_color)?;
+                None
+            }
+            // point
+            5 => {
+                self.input.skip(3 * 4 + nonessential_color)?;
+                None
+            }
+            // clipping
+            6 => {
+                self.input.read_varint()?;
+                let vertex_count = self.input.read_varint()?;
+                self.skip_vertices(vertex_count)?;
+                self.input.skip(nonessential_color)?;
+                None
+            }
+            other => anyhow::bail!("unknown attachment type {} of '{}'", other, name),
+        };
+
+        Ok(path.map(|path| (name, path)))
+    }
+
+    /// 4.1 起附件可以带序列帧信息
+    fn skip_sequence(&mut self) -> Result<()> {
+        if self.has_sequences && self.input.read_bool()? {
+            // count, start, digits, setupIndex
+            for _ in 0..4 {
+                self.input.read_varint()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_shorts(&mut self) -> Result<()> {
+        let count = self.input.read_varint()? as usize;
+        self.input.skip(count * 2)
+    }
+
+    /// 顶点可以是未加权的坐标，也可以是每个顶点若干个骨骼权重
+    fn skip_vertices(&mut self, vertex_count: u32) -> Result<()> {
+        if !self.input.read_bool()? {
+            return self.input.skip(vertex_count as usize * 2 * 4);
+        }
+        for _ in 0..vertex_count {
+            let bone_count = self.input.read_varint()?;
+            for _ in 0..bone_count {
+                self.input.read_varint()?;
+                self.input.skip(3 * 4)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_string(output: &mut Vec<u8>, value: &str) {
+    let mut length = value.len() as u32 + 1;
+    loop {
+        let byte = (length & 0x7F) as u8;
+        length >>= 7;
+        if length == 0 {
+            output.push(byte);
+            break;
+        }
+        output.push(byte | 0x80);
+    }
+    output.extend_from_slice(value.as_bytes());
+}
+
+/// 判断 "4.2.xx" 这样的版本号是否不低于 major.minor
+fn version_at_least(version: &str, major: u32, minor: u32) -> bool {
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let version_major = parts.next().unwrap_or(0);
+    let version_minor = parts.next().unwrap_or(0);
+    (version_major, version_minor) >= (major, minor)
+}
+
+/// 按 Spine 二进制格式读取数据（大端序，变长整数，长度前缀字符串）
+pub struct BinaryInput<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BinaryInput<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of data at byte {}", self.position))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    pub fn skip(&mut self, count: usize) -> Result<()> {
+        self.read_bytes(count).map(|_| ())
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.position + count;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of data at byte {}", self.position))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_byte()? != 0)
+    }
+
+    pub fn read_long(&mut self) -> Result<i64> {
+        let bytes = self.read_bytes(8)?;
+        Ok(i64::from_be_bytes(bytes.try_into()?))
+    }
+
+    pub fn read_float(&mut self) -> Result<f32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(f32::from_be_bytes(bytes.try_into()?))
+    }
+
+    /// 变长整数，每字节 7 位，最多 5 字节
+    pub fn read_varint(&mut self) -> Result<u32> {
+        let mut result: u32 = 0;
+        for shift in [0, 7, 14, 21, 28] {
+            let byte = self.read_byte()?;
+            result |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    /// 长度前缀为实际字节数加一，0 表示 null
+    pub fn read_string(&mut self) -> Result<Option<String>> {
+        let length = self.read_varint()? as usize;
+        if length == 0 {
+            return Ok(None);
+        }
+        let bytes = self.read_bytes(length - 1)?;
+        Ok(Some(String::from_utf8_lossy(bytes).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按 Spine 二进制格式写出测试数据
+    #[derive(Default)]
+    struct Writer(Vec<u8>);
+
+    impl Writer {
+        fn byte(&mut self, value: u8) -> &mut Self {
+            self.0.push(value);
+            self
+        }
+
+        fn bool(&mut self, value: bool) -> &mut Self {
+            self.byte(value as u8)
+        }
+
+        fn varint(&mut self, mut value: u32) -> &mut Self {
+            loop {
+                let byte = (value & 0x7F) as u8;
+                value >>= 7;
+                if value == 0 {
+                    return self.byte(byte);
+                }
+                self.byte(byte | 0x80);
+            }
+        }
+
+        fn string(&mut self, value: &str) -> &mut Self {
+            write_string(&mut self.0, value);
+            self
+        }
+
+        fn float(&mut self, value: f32) -> &mut Self {
+            self.0.extend_from_slice(&value.to_be_bytes());
+            self
+        }
+
+        fn long(&mut self, value: i64) -> &mut Self {
+            self.0.extend_from_slice(&value.to_be_bytes());
+            self
+        }
+
+        fn zeros(&mut self, count: usize) -> &mut Self {
+            self.0.resize(self.0.len() + count, 0);
+            self
+        }
+    }
+
+    /// 各版本的头部；nonessential 时图片目录为 ./images/
+    fn header_bytes(version: &str, nonessential: bool) -> Writer {
+        let mut writer = Writer::default();
+        if version.starts_with("4.") {
+            writer.long(0x0102030405060708);
+        } else {
+            writer.string("oldhash");
+        }
+        writer.string(version);
+        if version_at_least(version, 3, 8) {
+            writer.float(-10.0).float(-20.0);
+        }
+        writer.float(100.0).float(200.0);
+        if version_at_least(version, 4, 2) {
+            writer.float(1.0);
+        }
+        if version_at_least(version, 3, 8) {
+            writer.bool(nonessential);
+            if nonessential {
+                writer.float(30.0).string("./images/").string("");
+            }
+        }
+        writer
+    }
+
+    /// 一个骨骼、一个插槽，默认皮肤中有一个区域附件
+    fn skeleton(version: &str, nonessential: bool) -> Vec<u8> {
+        let mut writer = header_bytes(version, nonessential);
+        writer.varint(1).string("body");
+        // 骨骼
+        writer
+            .varint(1)
+            .string("root")
+            .zeros(8 * 4)
+            .varint(0)
+            .bool(false);
+        if nonessential {
+            writer.zeros(4);
+        }
+        // 插槽：名称、骨骼、颜色、初始附件（字符串表第 1 项）、混合模式
+        writer
+            .varint(1)
+            .string("body")
+            .varint(0)
+            .zeros(2 * 4)
+            .varint(1)
+            .varint(0);
+        // IK、变换、路径约束
+        writer.varint(0).varint(0).varint(0);
+        // 默认皮肤：插槽 0 的附件 body，名称和路径沿用占位名
+        writer.varint(1).varint(0).varint(1).varint(1).varint(0);
+        writer.byte(0).varint(0).zeros(7 * 4 + 4);
+        if version_at_least(version, 4, 1) {
+            writer.bool(false);
+        }
+        // 其他皮肤
+        writer.varint(0);
+        writer.0
+    }
+
+    #[test]
+    fn reads_header_of_each_version() {
+        for version in ["3.8.99", "4.0.64", "4.1.23", "4.2.18"] {
+            let header = SkeletonHeader::parse(&header_bytes(version, true).0).unwrap();
+            assert_eq!(header.version, version);
+            assert_eq!(
+                (header.x, header.y, header.width, header.height),
+                (-10.0, -20.0, 100.0, 200.0)
+            );
+            assert_eq!(header.fps, Some(30.0));
+            assert_eq!(header.images_path.as_deref(), Some("./images/"));
+            assert_eq!(header.audio_path.as_deref(), Some(""));
+            assert_eq!(
+                header.reference_scale,
+                version.starts_with("4.2").then_some(1.0)
+            );
+            if version.starts_with("4.") {
+                assert_eq!(header.hash, 0x0102030405060708_i64.to_string());
+            } else {
+                assert_eq!(header.hash, "oldhash");
+            }
+        }
+
+        let header = SkeletonHeader::parse(&header_bytes("4.1.23", false).0).unwrap();
+        assert_eq!((header.fps, header.images_path), (None, None));
+
+        // 3.8 之前没有包围盒位置，也不读取 nonessential 数据
+        let header = SkeletonHeader::parse(&header_bytes("3.7.94", false).0).unwrap();
+        assert_eq!((header.x, header.width, header.height), (0.0, 100.0, 200.0));
+    }
+
+    #[test]
+    fn reads_skins_of_each_version() {
+        for version in ["3.8.99", "4.0.64", "4.1.23"] {
+            for nonessential in [false, true] {
+                let skins = read_skins(&skeleton(version, nonessential)).unwrap();
+                assert_eq!(
+                    skins.slots,
+                    vec![("body".to_string(), Some("body".to_string()))]
+                );
+                assert_eq!(skins.attachments.len(), 1);
+                let attachment = &skins.attachments[0];
+                assert_eq!(
+                    (
+                        attachment.skin.as_str(),
+                        attachment.slot.as_str(),
+                        attachment.key.as_str(),
+                        attachment.name.as_str(),
+                        attachment.path.as_str()
+                    ),
+                    ("default", "body", "body", "body", "body")
+                );
+            }
+        }
+        assert!(read_skins(&skeleton("4.2.18", false)).is_err());
+    }
+
+    #[test]
+    fn rewrites_hash_and_keeps_the_rest() {
+        let data = skeleton("3.8.99", true);
+        let (output, hash) = rewrite_hash(&data, 42).unwrap();
+        assert_eq!(hash, crate::skeleton::encode_hash(42));
+        assert_eq!(SkeletonHeader::parse(&output).unwrap().hash, hash);
+        let old_hash_length = 1 + "oldhash".len();
+        assert_eq!(output[1 + hash.len()..], data[old_hash_length..]);
+
+        let data = skeleton("4.1.23", true);
+        let (output, hash) = rewrite_hash(&data, 42).unwrap();
+        assert_eq!(hash, "42");
+        assert_eq!(output[..8], 42_u64.to_be_bytes());
+        assert_eq!(output[8..], data[8..]);
+        assert_eq!(SkeletonHeader::parse(&output).unwrap().hash, "42");
+    }
+
+    #[test]
+    fn rewrites_images_path() {
+        let data = skeleton("4.1.23", true);
+        let output = rewrite_images_path(&data, "./hero_01/").unwrap().unwrap();
+        let header = SkeletonHeader::parse(&output).unwrap();
+        assert_eq!(header.images_path.as_deref(), Some("./hero_01/"));
+        assert!(read_skins(&output).is_ok());
+
+        let data = skeleton("4.1.23", false);
+        assert!(rewrite_images_path(&data, "./hero_01/").unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        for version in ["3.8.99", "4.0.64", "4.1.23"] {
+            let header_length = header_bytes(version, true).0.len();
+            let data = skeleton(version, true);
+            for length in 0..data.len() {
+                let truncated = &data[..length];
+                assert!(read_skins(truncated).is_err(), "{} at {}", version, length);
+                if length < header_length {
+                    assert!(SkeletonHeader::parse(truncated).is_err());
+                    assert!(rewrite_hash(truncated, 42).is_err());
+                    assert!(rewrite_images_path(truncated, "./hero_01/").is_err());
+                }
+            }
+        }
+    }
+}
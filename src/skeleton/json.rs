@@ -0,0 +1,425 @@
+use super::{SkeletonHeader, SkeletonSkins, SkinAttachment};
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+/// 从 JSON 骨骼的 skeleton 对象读取头部信息
+pub fn read_header(text: &str) -> Result<SkeletonHeader> {
+    let root: Value = serde_json::from_str(text).context("Invalid skeleton JSON")?;
+    let skeleton = root
+        .get("skeleton")
+        .ok_or_else(|| anyhow::anyhow!("Skeleton JSON has no \"skeleton\" object"))?;
+
+    let string = |key: &str| skeleton.get(key).and_then(Value::as_str).map(String::from);
+    let number = |key: &str| skeleton.get(key).and_then(Value::as_f64).map(|v| v as f32);
+
+    Ok(SkeletonHeader {
+        hash: string("hash").unwrap_or_default(),
+        version: string("spine").unwrap_or_default(),
+        x: number("x").unwrap_or(0.0),
+        y: number("y").unwrap_or(0.0),
+        width: number("width").unwrap_or(0.0),
+        height: number("height").unwrap_or(0.0),
+        reference_scale: number("referenceScale"),
+        fps: number("fps"),
+        images_path: string("images"),
+        audio_path: string("audio"),
+    })
+}
+
+/// 所有皮肤的 (皮肤名, 插槽名 → 附件名 → 附件) 列表
+pub fn skin_list(root: &Value) -> Vec<(&str, &Map<String, Value>)> {
+    match root.get("skins") {
+        // 3.8 起皮肤是带 name 的对象数组
+        Some(Value::Array(list)) => list
+            .iter()
+            .filter_map(|skin| {
+                let name = skin.get("name").and_then(Value::as_str)?;
+                Some((name, skin.get("attachments")?.as_object()?))
+            })
+            .collect(),
+        // 3.7 及以前皮肤是以名称为键的对象
+        Some(Value::Object(map)) => map
+            .iter()
+            .filter_map(|(name, skin)| Some((name.as_str(), skin.as_object()?)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 读取插槽的初始附件和所有皮肤中使用图集区域的附件
+pub fn read_skins(text: &str) -> Result<SkeletonSkins> {
+    let root: Value = serde_json::from_str(text).context("Invalid skeleton JSON")?;
+    let mut skins = SkeletonSkins::default();
+
+    for slot in root
+        .get("slots")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let name = slot.get("name").and_then(Value::as_str).unwrap_or_default();
+        let attachment = slot.get("attachment").and_then(Value::as_str);
+        skins
+            .slots
+            .push((name.to_string(), attachment.map(String::from)));
+    }
+
+    for (skin, slot_attachments) in skin_list(&root) {
+        for (slot, attachments) in slot_attachments {
+            let Some(attachments) = attachments.as_object() else {
+                continue;
+            };
+            for (key, attachment) in attachments {
+                let kind = attachment
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("region");
+                if !matches!(kind, "region" | "mesh" | "linkedmesh" | "skinnedmesh") {
+                    continue;
+                }
+                let name = attachment
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(key);
+                let path = attachment
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .unwrap_or(name);
+                skins.attachments.push(SkinAttachment {
+                    skin: skin.to_string(),
+                    slot: slot.clone(),
+                    key: key.clone(),
+                    name: name.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(skins)
+}
+
+/// 网格附件的纹理坐标和三角形
+#[derive(Debug, Clone)]
+pub struct MeshAttachment {
+    pub skin: String,
+    pub slot: String,
+    pub name: String,
+    /// 相对于区域原始图片的归一化坐标 u0, v0, u1, v1…，v 轴向下
+    pub uvs: Vec<f32>,
+    /// 顶点索引，每三个组成一个三角形
+    pub triangles: Vec<usize>,
+}
+
+/// 读取使用指定区域的网格附件，链接网格使用父网格的纹理坐标和三角形
+pub fn read_meshes(text: &str, region_name: &str) -> Result<Vec<MeshAttachment>> {
+    let root: Value = serde_json::from_str(text).context("Invalid skeleton JSON")?;
+    let skins = skin_list(&root);
+    let find_attachment = |skin_name: &str, slot: &str, name: &str| {
+        skins
+            .iter()
+            .find(|(skin, _)| *skin == skin_name)
+            .and_then(|(_, slots)| slots.get(slot))
+            .and_then(|attachments| attachments.get(name))
+    };
+
+    let mut meshes = Vec::new();
+    for (skin, slots) in &skins {
+        for (slot, attachments) in slots.iter() {
+            let Some(attachments) = attachments.as_object() else {
+                continue;
+            };
+            for (key, attachment) in attachments {
+                let name = attachment
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(key);
+                let path = attachment
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .unwrap_or(name);
+                if path != region_name {
+                    continue;
+                }
+
+                let mesh = match attachment.get("type").and_then(Value::as_str) {
+                    Some("mesh") | Some("skinnedmesh") => attachment,
+                    Some("linkedmesh") => {
+                        let parent = attachment
+                            .get("parent")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        let parent_skin = attachment
+                            .get("skin")
+                            .and_then(Value::as_str)
+                            .unwrap_or("default");
+                        find_attachment(parent_skin, slot, parent).ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Parent mesh '{}' of linked mesh '{}' not found",
+                                parent,
+                                name
+                            )
+                        })?
+                    }
+                    _ => continue,
+                };
+
+                let numbers = |key: &str| -> Vec<f64> {
+                    mesh.get(key)
+                        .and_then(Value::as_array)
+                        .map(|values| values.iter().filter_map(Value::as_f64).collect())
+                        .unwrap_or_default()
+                };
+                let uvs: Vec<f32> = numbers("uvs").into_iter().map(|v| v as f32).collect();
+                let triangles: Vec<usize> = numbers("triangles")
+                    .into_iter()
+                    .map(|index| index as usize)
+                    .collect();
+                if let Some(index) = triangles.iter().find(|index| **index * 2 + 1 >= uvs.len()) {
+                    anyhow::bail!(
+                        "Mesh '{}' in slot '{}' refers to missing vertex {}",
+                        name,
+                        slot,
+                        index
+                    );
+                }
+
+                meshes.push(MeshAttachment {
+                    skin: skin.to_string(),
+                    slot: slot.clone(),
+                    name: name.to_string(),
+                    uvs,
+                    triangles,
+                });
+            }
+        }
+    }
+
+    Ok(meshes)
+}
+
+/// 替换 skeleton 对象里的 hash，只改动这一个值，其余文本（缩进、字段顺序）保持不变
+///
+/// 没有 hash 字段时插入到 skeleton 对象的开头。
+pub fn rewrite_hash(text: &str, hash: &str) -> Result<String> {
+    let object_start = find_skeleton_object(text)?;
+
+    match find_member(text, object_start, "hash") {
+        Some((value_start, value_end)) if text.as_bytes()[value_start] == b'"' => Ok(format!(
+            "{}{}{}",
+            &text[..value_start + 1],
+            hash,
+            &text[value_end - 1..]
+        )),
+        Some(_) => anyhow::bail!("Skeleton JSON \"hash\" is not a string"),
+        None => {
+            // 紧跟在 '{' 之后插入
+            let insert_at = object_start + 1;
+            let separator = if text.as_bytes()[skip_whitespace(text.as_bytes(), insert_at)] == b'}'
+            {
+                ""
+            } else {
+                ","
+            };
+            Ok(format!(
+                "{}\"hash\":\"{}\"{}{}",
+                &text[..insert_at],
+                hash,
+                separator,
+                &text[insert_at..]
+            ))
+        }
+    }
+}
+
+/// 替换 skeleton 对象里的 images 路径，没有该字段时返回 None
+pub fn rewrite_images_path(text: &str, images_path: &str) -> Result<Option<String>> {
+    let object_start = find_skeleton_object(text)?;
+
+    Ok(find_member(text, object_start, "images")
+        .filter(|(value_start, _)| text.as_bytes()[*value_start] == b'"')
+        .map(|(value_start, value_end)| {
+            format!(
+                "{}{}{}",
+                &text[..value_start],
+                Value::from(images_path),
+                &text[value_end..]
+            )
+        }))
+}
+
+/// 返回根对象中 "skeleton" 键对应对象的 '{' 所在位置
+///
+/// 只查找根对象这一层的键，动画名、附件名等嵌套位置出现的 "skeleton" 不会被误认。
+fn find_skeleton_object(text: &str) -> Result<usize> {
+    let root_start = skip_whitespace(text.as_bytes(), 0);
+    find_member(text, root_start, "skeleton")
+        .map(|(value_start, _)| value_start)
+        .filter(|value_start| text.as_bytes()[*value_start] == b'{')
+        .ok_or_else(|| anyhow::anyhow!("Skeleton JSON has no \"skeleton\" object"))
+}
+
+/// 在 object_start 处的对象中查找键，返回值文本的起止位置（字符串包含引号）
+///
+/// 只比较这一层的键，嵌套的对象和数组整体跳过。
+fn find_member(text: &str, object_start: usize, key: &str) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    if bytes.get(object_start) != Some(&b'{') {
+        return None;
+    }
+    let mut position = skip_whitespace(bytes, object_start + 1);
+    if bytes.get(position) == Some(&b'}') {
+        return None;
+    }
+    loop {
+        let key_end = skip_string(bytes, position)?;
+        let colon = skip_whitespace(bytes, key_end);
+        if bytes.get(colon) != Some(&b':') {
+            return None;
+        }
+        let value_start = skip_whitespace(bytes, colon + 1);
+        let value_end = skip_value(bytes, value_start)?;
+        if serde_json::from_str::<String>(&text[position..key_end]).ok()? == key {
+            return Some((value_start, value_end));
+        }
+        position = skip_whitespace(bytes, value_end);
+        if bytes.get(position) != Some(&b',') {
+            return None;
+        }
+        position = skip_whitespace(bytes, position + 1);
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    while bytes.get(position).is_some_and(u8::is_ascii_whitespace) {
+        position += 1;
+    }
+    position
+}
+
+/// 跳过 position 处的字符串，返回结束引号之后的位置
+fn skip_string(bytes: &[u8], position: usize) -> Option<usize> {
+    if bytes.get(position) != Some(&b'"') {
+        return None;
+    }
+    let mut index = position + 1;
+    loop {
+        match bytes.get(index)? {
+            b'\\' => index += 2,
+            b'"' => return Some(index + 1),
+            _ => index += 1,
+        }
+    }
+}
+
+/// 跳过 position 处的任意值，返回值之后的位置
+fn skip_value(bytes: &[u8], position: usize) -> Option<usize> {
+    match bytes.get(position)? {
+        b'"' => skip_string(bytes, position),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut index = position;
+            loop {
+                match bytes.get(index)? {
+                    b'"' => {
+                        index = skip_string(bytes, index)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(index + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                index += 1;
+            }
+        }
+        _ => {
+            let end = position
+                + bytes[position..]
+                    .iter()
+                    .take_while(|byte| {
+                        !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+                    })
+                    .count();
+            (end > position).then_some(end)
+        }
+    }
+}
+
+/// 把染色写入插槽的初始姿势颜色，返回新的 JSON 文本
+///
+/// slot_names 为空时写入所有插槽。已有的 color 与染色相乘（保留原 alpha）；
+/// dark 给出时同时写入双色染色的暗色。
+pub fn tint_slots(
+    text: &str,
+    slot_names: &[String],
+    tint: [u8; 3],
+    dark: Option<[u8; 3]>,
+) -> Result<String> {
+    let mut root: Value = serde_json::from_str(text).context("Invalid skeleton JSON")?;
+    let slots = root
+        .get_mut("slots")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| anyhow::anyhow!("Skeleton JSON has no \"slots\" array"))?;
+
+    for slot_name in slot_names {
+        let found = slots
+            .iter()
+            .any(|slot| slot.get("name").and_then(Value::as_str) == Some(slot_name.as_str()));
+        if !found {
+            anyhow::bail!("Slot not found in skeleton: {}", slot_name);
+        }
+    }
+
+    for slot in slots.iter_mut() {
+        let name = slot
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if !slot_names.is_empty() && !slot_names.contains(&name) {
+            continue;
+        }
+        let Some(slot) = slot.as_object_mut() else {
+            continue;
+        };
+
+        let original = match slot.get("color").and_then(Value::as_str) {
+            Some(color) => parse_hex_color(color)
+                .ok_or_else(|| anyhow::anyhow!("Invalid color of slot '{}': {}", name, color))?,
+            None => [255; 4],
+        };
+        let channel = |i: usize| (original[i] as u32 * tint[i] as u32 + 127) / 255;
+        let color = format!(
+            "{:02x}{:02x}{:02x}{:02x}",
+            channel(0),
+            channel(1),
+            channel(2),
+            original[3]
+        );
+        slot.insert("color".to_string(), Value::from(color));
+
+        if let Some([r, g, b]) = dark {
+            let dark = format!("{:02x}{:02x}{:02x}", r, g, b);
+            slot.insert("dark".to_string(), Value::from(dark));
+        }
+    }
+
+    Ok(serde_json::to_string_pretty(&root)?)
+}
+
+/// 解析 RRGGBB 或 RRGGBBAA 形式的颜色
+fn parse_hex_color(value: &str) -> Option<[u8; 4]> {
+    if value.len() != 6 && value.len() != 8 {
+        return None;
+    }
+    let mut color = [255; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(value.len() / 2) {
+        *channel = u8::from_str_radix(value.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(color)
+}
@@ -0,0 +1,278 @@
+pub mod binary;
+pub mod json;
+
+pub use binary::SkeletonHeader;
+
+use crate::atlas::Atlas;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// 骨骼文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkeletonFormat {
+    /// 二进制 `.skel`
+    Binary,
+    /// JSON `.json`
+    Json,
+}
+
+impl SkeletonFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SkeletonFormat::Binary => "skel",
+            SkeletonFormat::Json => "json",
+        }
+    }
+}
+
+/// 与 Spine 文件同名的骨骼文件
+#[derive(Debug, Clone)]
+pub struct SkeletonFile {
+    pub path: String,
+    pub format: SkeletonFormat,
+}
+
+impl SkeletonFile {
+    /// 查找 `<spine_path>.skel` 和 `<spine_path>.json`，二进制在前
+    ///
+    /// 同名的 JSON 文件只有看起来像 Spine 骨骼（包含 bones）时才会被识别。
+    pub fn find_all(spine_path: &str) -> Vec<SkeletonFile> {
+        let mut skeletons = Vec::new();
+
+        let skel_path = format!("{}.skel", spine_path);
+        if Path::new(&skel_path).exists() {
+            skeletons.push(SkeletonFile {
+                path: skel_path,
+                format: SkeletonFormat::Binary,
+            });
+        }
+
+        let json_path = format!("{}.json", spine_path);
+        if is_spine_json(&json_path) {
+            skeletons.push(SkeletonFile {
+                path: json_path,
+                format: SkeletonFormat::Json,
+            });
+        }
+
+        skeletons
+    }
+
+    /// 读取插槽和皮肤中的附件
+    pub fn read_skins(&self) -> Result<SkeletonSkins> {
+        let skins = match self.format {
+            SkeletonFormat::Binary => fs::read(&self.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| binary::read_skins(&data)),
+            SkeletonFormat::Json => fs::read_to_string(&self.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| json::read_skins(&text)),
+        };
+        skins.with_context(|| format!("Failed to read skins: {}", self.path))
+    }
+
+    pub fn read_header(&self) -> Result<SkeletonHeader> {
+        match self.format {
+            SkeletonFormat::Binary => SkeletonHeader::load(&self.path),
+            SkeletonFormat::Json => {
+                let text = fs::read_to_string(&self.path)
+                    .with_context(|| format!("Failed to read skeleton file: {}", self.path))?;
+                json::read_header(&text)
+                    .with_context(|| format!("Failed to parse skeleton header: {}", self.path))
+            }
+        }
+    }
+}
+
+/// 皮肤中使用图集区域的一个附件
+#[derive(Debug, Clone)]
+pub struct SkinAttachment {
+    pub skin: String,
+    pub slot: String,
+    /// 皮肤中的占位名，插槽的初始附件名指向它
+    pub key: String,
+    pub name: String,
+    /// 使用的图集区域名
+    pub path: String,
+}
+
+/// 插槽的初始附件和所有皮肤中使用图集区域的附件
+#[derive(Debug, Clone, Default)]
+pub struct SkeletonSkins {
+    /// (插槽名, 初始姿势显示的附件名)
+    pub slots: Vec<(String, Option<String>)>,
+    pub attachments: Vec<SkinAttachment>,
+}
+
+impl SkeletonSkins {
+    /// 插槽在初始姿势中显示的附件，在各个皮肤中对应的区域（默认皮肤在前）
+    pub fn slot_attachments(&self, slot_name: &str) -> Result<Vec<&SkinAttachment>> {
+        let (_, setup_attachment) = self
+            .slots
+            .iter()
+            .find(|(name, _)| name == slot_name)
+            .ok_or_else(|| anyhow::anyhow!("Slot not found in skeleton: {}", slot_name))?;
+        let setup_attachment = setup_attachment.as_deref().ok_or_else(|| {
+            anyhow::anyhow!("Slot '{}' shows no attachment in the setup pose", slot_name)
+        })?;
+
+        Ok(self.sorted(|attachment| {
+            attachment.slot == slot_name && attachment.key == setup_attachment
+        }))
+    }
+
+    /// 指定名称的附件（任意插槽）在各个皮肤中对应的区域（默认皮肤在前）
+    pub fn named_attachments(&self, attachment_name: &str) -> Vec<&SkinAttachment> {
+        self.sorted(|attachment| {
+            attachment.key == attachment_name || attachment.name == attachment_name
+        })
+    }
+
+    fn sorted(&self, filter: impl Fn(&SkinAttachment) -> bool) -> Vec<&SkinAttachment> {
+        let mut attachments: Vec<&SkinAttachment> =
+            self.attachments.iter().filter(|a| filter(a)).collect();
+        attachments.sort_by_key(|attachment| attachment.skin != "default");
+        attachments
+    }
+}
+
+/// 重写骨骼文件中的哈希，返回写入的哈希文本
+pub fn rewrite_hash(
+    skeleton_path: &str,
+    format: SkeletonFormat,
+    content_hash: u64,
+) -> Result<String> {
+    match format {
+        SkeletonFormat::Binary => {
+            let data = fs::read(skeleton_path)?;
+            let (output, hash) = binary::rewrite_hash(&data, content_hash)
+                .with_context(|| format!("Failed to rewrite skeleton hash: {}", skeleton_path))?;
+            fs::write(skeleton_path, output)?;
+            Ok(hash)
+        }
+        SkeletonFormat::Json => {
+            let text = fs::read_to_string(skeleton_path)?;
+            let hash = encode_hash(content_hash);
+            let output = json::rewrite_hash(&text, &hash)
+                .with_context(|| format!("Failed to rewrite skeleton hash: {}", skeleton_path))?;
+            fs::write(skeleton_path, output)?;
+            Ok(hash)
+        }
+    }
+}
+
+/// 骨骼的图片目录中与 old_name 同名的路径段改为 new_name，返回修改后的目录
+///
+/// 例如 `./hero/` 会改为 `./hero_07/`，与名称无关的目录（如 `./images/`）保持不变。
+pub fn rename_images_path(
+    skeleton_path: &str,
+    format: SkeletonFormat,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Option<String>> {
+    let header = match format {
+        SkeletonFormat::Binary => SkeletonHeader::load(skeleton_path)?,
+        SkeletonFormat::Json => json::read_header(&fs::read_to_string(skeleton_path)?)?,
+    };
+    let Some(images_path) = header.images_path else {
+        return Ok(None);
+    };
+
+    let renamed: String = images_path
+        .split_inclusive(['/', '\\'])
+        .map(|component| {
+            let name = component.trim_end_matches(['/', '\\']);
+            if name == old_name {
+                format!("{}{}", new_name, &component[name.len()..])
+            } else {
+                component.to_string()
+            }
+        })
+        .collect();
+    if renamed == images_path {
+        return Ok(None);
+    }
+
+    let written = match format {
+        SkeletonFormat::Binary => binary::rewrite_images_path(&fs::read(skeleton_path)?, &renamed)?
+            .map(|output| fs::write(skeleton_path, output))
+            .transpose()?,
+        SkeletonFormat::Json => {
+            json::rewrite_images_path(&fs::read_to_string(skeleton_path)?, &renamed)?
+                .map(|output| fs::write(skeleton_path, output))
+                .transpose()?
+        }
+    };
+    Ok(written.map(|_| renamed))
+}
+
+/// 把变体的染色写入 JSON 骨骼的插槽颜色
+pub fn tint_slots(
+    skeleton_path: &str,
+    slot_names: &[String],
+    tint: [u8; 3],
+    dark: Option<[u8; 3]>,
+) -> Result<()> {
+    let text = fs::read_to_string(skeleton_path)?;
+    let output = json::tint_slots(&text, slot_names, tint, dark)
+        .with_context(|| format!("Failed to tint slots: {}", skeleton_path))?;
+    fs::write(skeleton_path, output)?;
+    Ok(())
+}
+
+/// 对若干段内容计算 64 位 FNV-1a 哈希，结果只取决于内容本身
+pub fn content_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in *part {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        // 分隔各段，避免拼接方式不同却得到相同的哈希
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 把哈希编码为不带填充的 base64 文本，与 Spine 编辑器导出的 JSON 哈希形式一致
+pub fn encode_hash(hash: u64) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let bytes = hash.to_be_bytes();
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let mut buffer = [0u8; 3];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let value = (buffer[0] as u32) << 16 | (buffer[1] as u32) << 8 | buffer[2] as u32;
+        // 3 字节对应 4 个字符，不足 3 字节时只输出有效的字符
+        for i in 0..=chunk.len() {
+            let index = (value >> (18 - i * 6)) & 0x3F;
+            encoded.push(ALPHABET[index as usize] as char);
+        }
+    }
+
+    encoded
+}
+
+fn is_spine_json(json_path: &str) -> bool {
+    fs::read_to_string(json_path)
+        .map(|text| text.contains("\"bones\""))
+        .unwrap_or(false)
+}
+
+/// 骨骼的 Spine 主版本与图集格式版本不一致时返回警告信息
+pub fn version_mismatch(header: &SkeletonHeader, atlas: &Atlas) -> Option<String> {
+    let skeleton_major = header.major_version()?;
+    let atlas_major = atlas.format.major_version();
+    if skeleton_major == atlas_major {
+        return None;
+    }
+
+    Some(format!(
+        "skeleton was exported by Spine {} but the atlas uses {} syntax; the runtime may fail to load them together",
+        header.version,
+        atlas.format.as_str()
+    ))
+}
@@ -0,0 +1,145 @@
+use crate::atlas::{Atlas, AtlasPage};
+use crate::geometry::PixelRect;
+use anyhow::Result;
+use std::path::Path;
+
+#[derive(clap::Args)]
+pub struct ValidateArgs {
+    /// Spine文件路径（不包含扩展名）
+    #[arg(help = "Spine文件路径，例如: ./data/lixiaolong")]
+    pub asset: String,
+
+    /// 要检查的文字矩形
+    #[arg(long, help = "列出与该矩形 x,y,width,height 相交的区域")]
+    pub rect: Option<PixelRect>,
+}
+
+/// 交叉检查图集与PNG纸张：纸张是否存在、尺寸是否一致、区域是否越界或互相重叠
+pub fn run(args: &ValidateArgs) -> Result<()> {
+    let atlas_path = format!("{}.atlas", args.asset);
+    let png_path = format!("{}.png", args.asset);
+
+    if !Path::new(&atlas_path).exists() {
+        anyhow::bail!("Atlas file does not exist: {}", atlas_path);
+    }
+
+    let atlas = Atlas::load(&atlas_path)?;
+    let atlas_dir = Path::new(&atlas_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+
+    println!(
+        "Validating {} ({}, {} pages)",
+        atlas_path,
+        atlas.format.as_str(),
+        atlas.pages.len()
+    );
+
+    let mut problems: Vec<String> = Vec::new();
+
+    for page in &atlas.pages {
+        // 纸张引用的图片相对于图集文件所在目录
+        let page_path = atlas_dir.join(&page.name);
+        match image::image_dimensions(&page_path) {
+            Ok((width, height)) => {
+                if (width, height) != (page.width, page.height) {
+                    problems.push(format!(
+                        "page '{}' is declared as {}x{} but the image is {}x{}",
+                        page.name, page.width, page.height, width, height
+                    ));
+                }
+            }
+            Err(error) => problems.push(format!(
+                "page '{}' cannot be read from {}: {}",
+                page.name,
+                page_path.display(),
+                error
+            )),
+        }
+
+        check_region_bounds(page, &mut problems);
+        check_region_overlaps(page, &mut problems);
+    }
+
+    if let Some(rect) = &args.rect {
+        report_rect_intersections(&atlas, &png_path, rect);
+    }
+
+    if problems.is_empty() {
+        println!("Validation passed: atlas and textures are consistent.");
+        Ok(())
+    } else {
+        for problem in &problems {
+            println!("Error: {}", problem);
+        }
+        anyhow::bail!("Validation failed with {} problem(s)", problems.len())
+    }
+}
+
+fn check_region_bounds(page: &AtlasPage, problems: &mut Vec<String>) {
+    let page_bounds = PixelRect::new(0, 0, page.width, page.height);
+
+    for region in &page.regions {
+        let bounds = region.packed_bounds();
+        if !page_bounds.contains_rect(&bounds) {
+            problems.push(format!(
+                "region '{}' ({}) lies outside page '{}' ({}x{})",
+                region.name, bounds, page.name, page.width, page.height
+            ));
+        }
+    }
+}
+
+fn check_region_overlaps(page: &AtlasPage, problems: &mut Vec<String>) {
+    for (i, first) in page.regions.iter().enumerate() {
+        for second in &page.regions[i + 1..] {
+            let first_bounds = first.packed_bounds();
+            let second_bounds = second.packed_bounds();
+
+            // 完全相同的范围是打包时合并的重复图片，不算重叠
+            if first_bounds == second_bounds {
+                continue;
+            }
+
+            if let Some(overlap) = first_bounds.intersect(&second_bounds) {
+                problems.push(format!(
+                    "regions '{}' and '{}' on page '{}' overlap at {}",
+                    first.name, second.name, page.name, overlap
+                ));
+            }
+        }
+    }
+}
+
+fn report_rect_intersections(atlas: &Atlas, png_path: &str, rect: &PixelRect) {
+    let Some(page) = atlas.page_for_image(png_path) else {
+        println!(
+            "No atlas page matches {}, cannot check the rectangle",
+            png_path
+        );
+        return;
+    };
+
+    println!("Rectangle {} on page '{}':", rect, page.name);
+
+    let mut found = false;
+    for region in &page.regions {
+        let bounds = region.packed_bounds();
+        if let Some(overlap) = bounds.intersect(rect) {
+            found = true;
+            let coverage = if bounds.contains_rect(rect) {
+                "fully inside"
+            } else {
+                "partially inside"
+            };
+            println!(
+                "  {} region '{}' ({}), overlap {}",
+                coverage, region.name, bounds, overlap
+            );
+        }
+    }
+
+    if !found {
+        println!("  does not intersect any region");
+    }
+}